@@ -0,0 +1,32 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use wolfenstein::game_logic::Game;
+
+// The same rays through both casters, from every open cell of the built in map
+fn casters(c: &mut Criterion) 
+{
+    let game = Game::new();
+    let rays = game.caster_rays(180);
+
+    let mut group = c.benchmark_group("casters");
+
+    group.bench_function("march_ray", |b| {
+        b.iter(|| {
+            for (origin, angle) in rays.iter() {
+                black_box(game.march_ray(*origin, *angle));
+            }
+        })
+    });
+
+    group.bench_function("march_ray_dda", |b| {
+        b.iter(|| {
+            for (origin, angle) in rays.iter() {
+                black_box(game.march_ray_dda(*origin, *angle));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, casters);
+criterion_main!(benches);
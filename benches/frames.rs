@@ -0,0 +1,129 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use wolfenstein::game_logic::{Game, ViewMode};
+use wolfenstein::terminal::input::keys::{Key, KeyState};
+use wolfenstein::terminal::output::{ColorMode, Renderer};
+use wolfenstein::Vec2;
+
+// Throws the output away like io::sink() but counts the bytes on the way
+struct CountingWriter 
+{
+    written: Arc<AtomicUsize>,
+}
+
+impl std::io::Write for CountingWriter 
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> 
+    {
+        self.written.fetch_add(buf.len(), Ordering::Relaxed);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> 
+    {
+        Ok(())
+    }
+}
+
+// A whole frame, stepping the game, drawing it and writing it out, in every view mode
+fn frames(c: &mut Criterion) 
+{
+    let mut group = c.benchmark_group("frames");
+
+    for mode in [ViewMode::Mode3d, ViewMode::Mode2d, ViewMode::Mode2dAnd3d, ViewMode::Wireframe] 
+    {
+        let mut output = Renderer::with_writer(Box::new(std::io::sink()));
+        let mut game = Game::new();
+        output.set_dimensions(120, 80);
+
+        group.bench_function(format!("{:?}", mode), |b| {
+            b.iter(|| {
+                output.update();
+                game.step(KeyState::default(), 0.05);
+                game.render(&mut output, mode);
+                output.render();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// The 3d view turning every frame, so most of the screen changes, in every color mode. The 
+// bytes one frame writes are printed first and given to criterion as the throughput
+fn color_modes(c: &mut Criterion) 
+{
+    let mut group = c.benchmark_group("color_modes");
+    let turning = KeyState::default().with(Key::ArrowRight);
+
+    for mode in [ColorMode::Monochrome, ColorMode::Ansi256, ColorMode::TrueColor] 
+    {
+        let written = Arc::new(AtomicUsize::new(0));
+        let mut output = Renderer::with_writer(Box::new(CountingWriter { written: written.clone() }));
+        let mut game = Game::new();
+        output.set_dimensions(120, 80);
+        output.set_color_mode(mode);
+
+        let mut frame = || {
+            output.update();
+            game.step(turning, 0.05);
+            game.render(&mut output, ViewMode::Mode3d);
+            output.render();
+        };
+
+        // The first frame repaints everything, the ones after it only what changed
+        frame();
+        written.store(0, Ordering::Relaxed);
+
+        const MEASURED_FRAMES: usize = 100;
+        for _ in 0..MEASURED_FRAMES 
+        {
+            frame();
+        }
+
+        let per_frame = written.load(Ordering::Relaxed) / MEASURED_FRAMES;
+        println!("color_modes/{:?}: {} bytes per frame", mode, per_frame);
+        group.throughput(Throughput::Bytes(per_frame as u64));
+        group.bench_function(format!("{:?}", mode), |b| b.iter(&mut frame));
+    }
+
+    group.finish();
+}
+
+// A blank 400x100 screen with a few hundred cells lit in different places every frame, so 
+// render() has to find many short changed runs between long unchanged ones
+fn scattered_changes(c: &mut Criterion) 
+{
+    let mut group = c.benchmark_group("scattered_changes");
+
+    for mode in [ColorMode::Monochrome, ColorMode::TrueColor] 
+    {
+        let mut output = Renderer::with_writer(Box::new(std::io::sink()));
+        output.set_dimensions(400, 100);
+        output.set_color_mode(mode);
+
+        let mut seed: u32 = 1;
+        group.bench_function(format!("{:?}", mode), |b| {
+            b.iter(|| {
+                output.update();
+
+                for _ in 0..400 
+                {
+                    seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                    let cell = (seed >> 8) as i32 % (400 * 100);
+
+                    output.draw_point_unnormalized(Vec2 { x: cell % 400, y: cell / 400 }, b'#');
+                }
+
+                output.render();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, frames, color_modes, scattered_changes);
+criterion_main!(benches);
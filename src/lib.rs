@@ -0,0 +1,8764 @@
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vec2<T> 
+{
+    pub x: T,
+    pub y: T,
+}
+
+impl core::fmt::Display for Vec2<f32> 
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result 
+    {
+        write!(f, "[{}, {}]", self.x, self.y)
+    }
+}
+
+// Why text isn't a Vec2
+#[derive(Clone, PartialEq, Debug)]
+pub enum ParseVec2Error 
+{
+    // Not two values split by a comma, or only one of the brackets
+    WrongShape,
+    // One of the values, as it was written
+    BadNumber(String),
+}
+
+impl core::fmt::Display for ParseVec2Error 
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result 
+    {
+        match self {
+            ParseVec2Error::WrongShape => write!(f, "needs an x and a y split by a comma"),
+            ParseVec2Error::BadNumber(number) => write!(f, "has a bad number \"{}\"", number),
+        }
+    }
+}
+
+impl std::error::Error for ParseVec2Error {}
+
+// "x, y", or "[x, y]" like Display writes it, spaces around the values don't matter
+impl std::str::FromStr for Vec2<f32> 
+{
+    type Err = ParseVec2Error;
+
+    fn from_str(s: &str) -> Result<Vec2<f32>, ParseVec2Error> 
+    {
+        let s = s.trim();
+        let inner = match (s.strip_prefix('['), s.ends_with(']')) {
+            (Some(rest), true) => &rest[..rest.len() - 1],
+            (None, false) => s,
+            _ => return Err(ParseVec2Error::WrongShape),
+        };
+
+        let (x, y) = inner.split_once(',')
+            .filter(|(_, y)| !y.contains(','))
+            .ok_or(ParseVec2Error::WrongShape)?;
+        let parse = |value: &str| {
+            let value = value.trim();
+            value.parse::<f32>().map_err(|_| ParseVec2Error::BadNumber(value.to_string()))
+        };
+
+        Ok(Vec2 { x: parse(x)?, y: parse(y)? })
+    }
+}
+
+// Component-wise, x and y are compared independently of each other
+impl<T: PartialOrd + Copy> Vec2<T> 
+{
+    pub fn min(&self, other: Vec2<T>) -> Vec2<T> 
+    {
+        Vec2 { 
+            x: if other.x < self.x { other.x } else { self.x },
+            y: if other.y < self.y { other.y } else { self.y },
+        }
+    }
+
+    pub fn max(&self, other: Vec2<T>) -> Vec2<T> 
+    {
+        Vec2 { 
+            x: if other.x > self.x { other.x } else { self.x },
+            y: if other.y > self.y { other.y } else { self.y },
+        }
+    }
+
+    // Keeps the vector inside the rectangle spanned by min and max
+    pub fn clamp(&self, min: Vec2<T>, max: Vec2<T>) -> Vec2<T> 
+    {
+        self.max(min).min(max)
+    }
+}
+
+impl Vec2<f32> 
+{
+    // Unit vector for an angle in radians, 0 points up the map (towards -y) and angles grow clockwise
+    pub fn from_angle(angle: f32) -> Vec2<f32> 
+    {
+        Vec2 { x: angle.sin(), y: -angle.cos() }
+    }
+
+    // Inverse of from_angle(), the angle the vector points at, from 0 to 2 PI
+    pub fn angle(&self) -> f32 
+    {
+        let angle = self.x.atan2(-self.y);
+
+        if angle < 0. { angle + std::f32::consts::TAU } else { angle }
+    }
+
+    pub fn dot(&self, other: Vec2<f32>) -> f32 
+    {
+        self.x * other.x + self.y * other.y
+    }
+
+    // Z of the 3d cross product, its sign tells on which side of self the other vector is
+    pub fn cross(&self, other: Vec2<f32>) -> f32 
+    {
+        self.x * other.y - self.y * other.x
+    }
+
+    // Point t of the way from self to other, t outside of 0 to 1 goes past either end
+    pub fn lerp(&self, other: Vec2<f32>, t: f32) -> Vec2<f32> 
+    {
+        Vec2 { x: self.x + (other.x - self.x) * t, y: self.y + (other.y - self.y) * t }
+    }
+
+    // Same as lerp(), but never past self or other
+    pub fn lerp_clamped(&self, other: Vec2<f32>, t: f32) -> Vec2<f32> 
+    {
+        self.lerp(other, t.clamp(0., 1.))
+    }
+
+    // Mirrors the vector off a surface, normal has to be of unit length
+    pub fn reflect(&self, normal: Vec2<f32>) -> Vec2<f32> 
+    {
+        let d = 2. * self.dot(normal);
+
+        Vec2 { x: self.x - d * normal.x, y: self.y - d * normal.y }
+    }
+}
+
+
+
+fn points_distance(pos1: Vec2<f32>, pos2: Vec2<f32>) -> f32 
+{
+    ((pos2.x - pos1.x).powf(2.) + (pos2.y - pos1.y).powf(2.)).sqrt()
+}
+
+#[cfg(test)]
+mod tests 
+{
+    use super::*;
+
+    // Vec2 has no PartialEq, tuples of its components do
+    fn xy<T>(v: Vec2<T>) -> (T, T) 
+    {
+        (v.x, v.y)
+    }
+
+    #[test]
+    fn lerp_clamped_stays_between_the_ends() 
+    {
+        let from = Vec2 { x: 2., y: -4. };
+        let to = Vec2 { x: 6., y: 8. };
+
+        assert_eq!(xy(from.lerp_clamped(to, 0.)), (2., -4.));
+        assert_eq!(xy(from.lerp_clamped(to, 1.)), (6., 8.));
+        assert_eq!(xy(from.lerp_clamped(to, 0.5)), (4., 2.));
+        assert_eq!(xy(from.lerp_clamped(to, -1.)), (2., -4.));
+        assert_eq!(xy(from.lerp_clamped(to, 3.)), (6., 8.));
+        assert_eq!(xy(from.lerp(to, 1.5)), (8., 14.));
+    }
+
+    // Positive when the other vector is clockwise of self, north to east
+    #[test]
+    fn dot_and_cross_of_orthogonal_and_parallel_vectors() 
+    {
+        let north = Vec2::from_angle(0.);
+        let east = Vec2 { x: 1., y: 0. };
+        let along = Vec2 { x: 0., y: -3. };
+
+        assert_eq!(north.dot(east), 0.);
+        assert_eq!(north.cross(east), 1.);
+        assert_eq!(east.cross(north), -1.);
+        assert_eq!(north.cross(along), 0.);
+        assert_eq!(north.dot(along), 3.);
+    }
+
+    // Off a wall hit on the x axis only x turns around, on the y axis only y
+    #[test]
+    fn reflect_off_axis_normals() 
+    {
+        let diagonal = Vec2 { x: 1., y: 1. };
+
+        assert_eq!(xy(diagonal.reflect(Vec2 { x: -1., y: 0. })), (-1., 1.));
+        assert_eq!(xy(diagonal.reflect(Vec2 { x: 0., y: -1. })), (1., -1.));
+        assert_eq!(xy(diagonal.reflect(Vec2 { x: 0., y: 1. })), (1., -1.));
+    }
+
+    #[test]
+    fn clamp_goes_by_each_component() 
+    {
+        let min = Vec2 { x: 0, y: 10 };
+        let max = Vec2 { x: 5, y: 20 };
+
+        assert_eq!(xy(Vec2 { x: -3, y: 15 }.clamp(min, max)), (0, 15));
+        assert_eq!(xy(Vec2 { x: 9, y: 15 }.clamp(min, max)), (5, 15));
+        assert_eq!(xy(Vec2 { x: 2, y: 4 }.clamp(min, max)), (2, 10));
+        assert_eq!(xy(Vec2 { x: 2, y: 40 }.clamp(min, max)), (2, 20));
+        assert_eq!(xy(Vec2 { x: -1, y: 40 }.clamp(min, max)), (0, 20));
+        assert_eq!(xy(Vec2 { x: 2, y: 15 }.clamp(min, max)), (2, 15));
+
+        let position = Vec2 { x: 2.5, y: -1. };
+        assert_eq!(xy(position.min(Vec2 { x: 1., y: 0. })), (1., -1.));
+        assert_eq!(xy(position.max(Vec2 { x: 1., y: 0. })), (2.5, 0.));
+    }
+
+    #[test]
+    fn vec2_parses_what_display_writes() 
+    {
+        let parse = |s: &str| s.parse::<Vec2<f32>>().map(xy);
+        let position = Vec2 { x: 37.5, y: -2. };
+
+        assert_eq!(parse(&position.to_string()), Ok((37.5, -2.)));
+        assert_eq!(parse("3,4"), Ok((3., 4.)));
+        assert_eq!(parse("  [ 3 ,\t4 ] "), Ok((3., 4.)));
+        assert_eq!(parse(" 3 , 4 "), Ok((3., 4.)));
+
+        assert_eq!(parse(""), Err(ParseVec2Error::WrongShape));
+        assert_eq!(parse("3"), Err(ParseVec2Error::WrongShape));
+        assert_eq!(parse("1, 2, 3"), Err(ParseVec2Error::WrongShape));
+        assert_eq!(parse("[3, 4"), Err(ParseVec2Error::WrongShape));
+        assert_eq!(parse("3, 4]"), Err(ParseVec2Error::WrongShape));
+        assert_eq!(parse("3, four"), Err(ParseVec2Error::BadNumber("four".to_string())));
+        assert_eq!(parse(", 4"), Err(ParseVec2Error::BadNumber(String::new())));
+    }
+}
+
+
+
+#[cfg(windows)]
+mod windows_errors 
+{
+    pub fn get_last_error() -> u32 
+    {
+        use winapi::um::errhandlingapi::GetLastError;
+        
+        unsafe { 
+            GetLastError() 
+        }
+    }
+}
+
+
+
+pub mod terminal 
+{
+    pub mod output 
+    {
+        use crate::Vec2;
+        use std::mem::swap;
+
+        pub const CHAR_EMPTY:     u8 = b' ';
+        pub const BLACK_BOX_CHAR: u8 = b'@';
+        pub const STRIP_BOX_CHAR: u8 = b'-';
+        pub const AT_CHAR:        u8 = b'@';
+        pub const DASH_CHAR:      u8 = b'-';
+        pub const SPARK_CHAR:     u8 = b'*';
+        pub const WALL_2D_CHAR:   u8 = b'#';
+        pub const EXIT_2D_CHAR:   u8 = b'E';
+        pub const PLAYER_2D_CHAR: u8 = b'O';
+        pub const FACING_CHAR:    u8 = b'+';
+        pub const RING_CHAR:      u8 = b'.';
+        pub const CONE_CHAR:      u8 = b':';
+        pub const HIT_CHAR:       u8 = b'x';
+        pub const CROSSHAIR_CHAR: u8 = b'+';
+        pub const ACTOR_CHAR:     u8 = b'&';
+        pub const DIVIDER_CHAR:   u8 = b'|';
+
+        // From the faintest to the densest looking character
+        pub const SHADING_RAMP: &[u8] = b".:-=+*#%@";
+
+        #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+        pub struct Color 
+        {
+            pub r: u8,
+            pub g: u8,
+            pub b: u8,
+        }
+
+        impl Color 
+        {
+            pub const WHITE: Color = Color { r: 255, g: 255, b: 255 };
+
+            // Brightens or darkens every channel, saturating at 0 and 255
+            pub fn scale(&self, factor: f32) -> Color 
+            {
+                let channel = |c: u8| (c as f32 * factor).round().clamp(0., 255.) as u8;
+
+                Color { r: channel(self.r), g: channel(self.g), b: channel(self.b) }
+            }
+
+            // Closest color of the 6x6x6 cube in the xterm 256 color palette
+            pub fn to_ansi256(&self) -> u8 
+            {
+                let level = |c: u8| (c as u16 * 5 + 127) / 255;
+
+                (16 + 36 * level(self.r) + 6 * level(self.g) + level(self.b)) as u8
+            }
+        }
+
+        pub const DEFAULT_COLOR: Color = Color::WHITE;
+
+        #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+        pub enum ColorMode 
+        {
+            Monochrome,
+            Ansi256,
+            TrueColor,
+        }
+
+        // Named bundles of the look settings, Game::apply_preset() sets them all, the renderer's share 
+        // through Renderer::apply_preset(). Settings changed afterwards stay changed
+        #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+        pub enum RenderPreset 
+        {
+            Default,
+            // Two wall characters that can't be mistaken for each other, no distance fog,
+            // both wall sides equally bright, and bold colors
+            HighContrast,
+        }
+
+        impl RenderPreset 
+        {
+            pub fn from_name(name: &str) -> Option<RenderPreset> 
+            {
+                match name {
+                    "default" => Some(RenderPreset::Default),
+                    "high_contrast" => Some(RenderPreset::HighContrast),
+                    _ => None,
+                }
+            }
+
+            // Characters of the walls hit on the x and on the y axis
+            pub fn wall_chars(&self) -> (u8, u8) 
+            {
+                match self {
+                    RenderPreset::Default => (BLACK_BOX_CHAR, STRIP_BOX_CHAR),
+                    RenderPreset::HighContrast => (b'#', b':'),
+                }
+            }
+
+            pub fn side_shade(&self) -> f32 
+            {
+                match self {
+                    RenderPreset::Default => crate::game_logic::DEFAULT_SIDE_SHADE,
+                    RenderPreset::HighContrast => 1.,
+                }
+            }
+
+            pub fn distance_fog(&self) -> bool 
+            {
+                *self == RenderPreset::Default
+            }
+
+            pub fn bold_colors(&self) -> bool 
+            {
+                *self == RenderPreset::HighContrast
+            }
+        }
+
+        // The windows console gets plain WriteConsoleA calls which don't understand escape codes
+        #[cfg(unix)]
+        const DEFAULT_COLOR_MODE: ColorMode = ColorMode::TrueColor;
+        #[cfg(windows)]
+        const DEFAULT_COLOR_MODE: ColorMode = ColorMode::Monochrome;
+
+        #[derive(Clone)]
+        pub struct FrameBuffer 
+        {
+            data: Vec<u8>,
+            colors: Vec<Color>,
+            width: i16,
+            height: i16,
+        }
+
+        impl Default for FrameBuffer 
+        {
+            fn default() -> FrameBuffer 
+            {
+                FrameBuffer::new()
+            }
+        }
+
+        impl FrameBuffer 
+        {
+            pub fn new() -> FrameBuffer 
+            {
+                FrameBuffer {
+                    data: Vec::new(),
+                    colors: Vec::new(),
+                    width: 0,
+                    height: 0,
+                }
+            }
+
+            pub fn width(&self) -> i16 
+            {
+                self.width
+            }
+
+            pub fn height(&self) -> i16 
+            {
+                self.height
+            }
+
+            pub fn len(&self) -> usize 
+            {
+                self.data.len()
+            }
+
+            pub fn is_empty(&self) -> bool 
+            {
+                self.data.is_empty()
+            }
+
+            pub fn data(&self) -> &[u8] 
+            {
+                &self.data
+            }
+
+            pub fn colors(&self) -> &[Color] 
+            {
+                &self.colors
+            }
+
+            // Binary PPM image of the frame, every cell a block of cell_width pixels across and twice 
+            // that down, as bright as its character is dense and in its color, or gray without color
+            pub fn write_ppm(&self,
+                             out: &mut impl std::io::Write,
+                             cell_width: usize,
+                             color: bool) -> std::io::Result<()> 
+            {
+                let cell_height = cell_width * 2;
+                let width = self.width.max(0) as usize;
+
+                write!(out, "P6\n{} {}\n255\n", width * cell_width, self.height.max(0) as usize * cell_height)?;
+
+                let mut row = Vec::with_capacity(width * cell_width * 3);
+                for y in 0..self.height.max(0) as usize 
+                {
+                    row.clear();
+
+                    for x in 0..width 
+                    {
+                        let i = y * width + x;
+                        let tint = if color { self.colors[i] } else { Color::WHITE };
+                        let pixel = tint.scale(ink_density(self.data[i]));
+
+                        for _ in 0..cell_width {
+                            row.extend_from_slice(&[pixel.r, pixel.g, pixel.b]);
+                        }
+                    }
+
+                    for _ in 0..cell_height {
+                        out.write_all(&row)?;
+                    }
+                }
+
+                Ok(())
+            }
+
+            // None when the position is outside of the buffer. The multiplication is done in usize, 
+            // so it can't overflow, and the index is checked against the data too, in case it ever 
+            // gets out of step with the width and height
+            #[inline]
+            pub fn index(&self, x: i32, y: i32) -> Option<usize> 
+            {
+                if (x >= self.width as i32) ||
+                   (y >= self.height as i32) ||
+                   (x < 0) || (y < 0) 
+                {
+                    return None;
+                }
+
+                let i = y as usize * self.width as usize + x as usize;
+                if i >= self.data.len() || i >= self.colors.len() {
+                    return None;
+                }
+
+                Some(i)
+            }
+
+            #[inline]
+            pub fn coords(&self, index: usize) -> Vec2<i16> 
+            {
+                Vec2 { 
+                    x: (index % self.width as usize) as i16,
+                    y: (index / self.width as usize) as i16,
+                }
+            }
+
+            #[inline]
+            pub fn get(&self, x: i32, y: i32) -> Option<u8> 
+            {
+                self.index(x, y).map(|i| self.data[i])
+            }
+
+            #[inline]
+            pub fn get_color(&self, x: i32, y: i32) -> Option<Color> 
+            {
+                self.index(x, y).map(|i| self.colors[i])
+            }
+
+            #[inline]
+            pub fn set(&mut self, x: i32, y: i32, ch: u8, color: Color) 
+            {
+                if let Some(i) = self.index(x, y) {
+                    self.data[i] = ch;
+                    self.colors[i] = color;
+                }
+            }
+
+            pub fn fill(&mut self, ch: u8) 
+            {
+                for i in self.data.iter_mut() {
+                    *i = ch;
+                }
+
+                for c in self.colors.iter_mut() {
+                    *c = DEFAULT_COLOR;
+                }
+            }
+
+            // Contents don't survive a resize, the whole buffer is filled with ch
+            pub fn resize(&mut self, width: i16, height: i16, ch: u8) 
+            {
+                self.width = width.max(0);
+                self.height = height.max(0);
+
+                let len = self.width as usize * self.height as usize;
+
+                self.data.clear();
+                self.data.resize(len, ch);
+                self.colors.clear();
+                self.colors.resize(len, DEFAULT_COLOR);
+            }
+        }
+
+        // A 4K screen with a tiny font can have millions of cells, far more than the picture needs
+        pub const DEFAULT_MAX_CELLS: usize = 250_000;
+
+        // Not a printable character, so no picture needs it for anything else
+        pub const TRANSPARENT_CHAR: u8 = 0;
+
+        const CLEAR_SEQUENCE: &[u8] = b"\x1B[2J";
+        // Default colors, clear, cursor home and visible
+        const RESET_SEQUENCE: &[u8] = b"\x1B[0m\x1B[2J\x1B[H\x1B[?25h";
+
+        // Without a console to query, headless builds render into a fixed size frame
+        #[cfg(feature = "headless")]
+        const HEADLESS_DIMENSIONS: Vec2<i16> = Vec2 { x: 80, y: 48 };
+
+        const FRONT_INDEX: usize = 0;
+        const BACK_INDEX:  usize = 1;
+
+        // Pixels across a terminal cell in recorded frames, cells are twice as tall
+        pub const DEFAULT_FRAME_CELL_WIDTH: usize = 4;
+
+        // In columns, the size draw_dot() always had
+        pub const DEFAULT_DOT_RADIUS: f32 = 3.;
+
+        pub struct Renderer 
+        {
+            screen_dimensions: Vec2<i16>,
+            swap_chain: Vec<FrameBuffer>,
+            force_redraw: bool,
+            transparent_char: u8,
+            color_mode: ColorMode,
+            // Bold text in the color modes, most terminals draw it brighter too
+            bold_colors: bool,
+            // Color of everything drawn until the next set_color()
+            current_color: Color,
+            // Reused for building colored runs in render_frame
+            color_scratch: Vec<u8>,
+            // Follow the console size on every update(), off once the dimensions are set by hand
+            auto_resize: bool,
+            sink: Sink,
+            // Consoles bigger than this many cells get a smaller viewport centered in them
+            max_cells: usize,
+            // Console cell of the viewport's top left corner
+            origin: Vec2<i16>,
+            // The viewport changed size since the last render()
+            resized: bool,
+            recording: Option<FrameRecording>,
+            // Why the last recording stopped early
+            recording_error: Option<String>,
+            frame_cell_width: usize,
+            frame_color: bool,
+            // Drawn by every update(), in the order they were added, under anything drawn after it
+            objects: Vec<(ObjectId, Box<dyn Drawable + Send>)>,
+            next_object_id: u32,
+            // Height of a console cell divided by its width, for shapes that should look round
+            cell_aspect: f32,
+            // Set while a Viewport is alive, drawing is clipped to it and moved to its corner
+            viewport: Option<ViewportArea>,
+        }
+
+        #[derive(Copy, Clone)]
+        struct ViewportArea 
+        {
+            // Console cell of the top left corner, in the whole screen
+            origin: Vec2<i32>,
+            // Same units as get_screen_dim(), cells across and half cells down
+            screen: Vec2<i16>,
+        }
+
+        // A rectangle of the screen that's drawn into like a screen of its own, see Renderer::viewport(). 
+        // The renderer goes back to the previous viewport when it's dropped
+        pub struct Viewport<'a> 
+        {
+            renderer: &'a mut Renderer,
+            previous: Option<ViewportArea>,
+        }
+
+        impl std::ops::Deref for Viewport<'_> 
+        {
+            type Target = Renderer;
+
+            fn deref(&self) -> &Renderer 
+            {
+                self.renderer
+            }
+        }
+
+        impl std::ops::DerefMut for Viewport<'_> 
+        {
+            fn deref_mut(&mut self) -> &mut Renderer 
+            {
+                self.renderer
+            }
+        }
+
+        impl Drop for Viewport<'_> 
+        {
+            fn drop(&mut self) 
+            {
+                self.renderer.viewport = self.previous;
+            }
+        }
+
+        // Something on the screen every frame, added once with add_object() instead of drawn by hand. 
+        // Drawn over whatever else the frame has, right before render() shows it
+        pub trait Drawable 
+        {
+            fn draw(&self, output: &mut Renderer);
+        }
+
+        // Handed out by add_object(), never reused by the same renderer
+        #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+        pub struct ObjectId(u32);
+
+        // Writes every rendered frame into its own image file
+        struct FrameRecording 
+        {
+            dir: std::path::PathBuf,
+            written: u32,
+            max_frames: u32,
+        }
+
+        // Where render_frame() sends its output
+        enum Sink 
+        {
+            Console,
+            // Gets the same ANSI sequences a unix console would, on every platform
+            Writer(Box<dyn std::io::Write + Send>),
+        }
+
+        impl Sink 
+        {
+            fn set_cursor_position(&mut self, origin: Vec2<i16>, dim: Vec2<i16>) 
+            {
+                let dim = Vec2 { x: origin.x + dim.x, y: origin.y + dim.y };
+
+                match self {
+                    Sink::Console => set_cursor_position(dim),
+                    Sink::Writer(w) => write_ansi_cursor_position(w, dim)
+                        .expect("Cannot write cursor position to the output writer"),
+                }
+            }
+
+            fn output_array(&mut self, arr: &[u8]) 
+            {
+                match self {
+                    Sink::Console => output_array(arr),
+                    Sink::Writer(w) => w.write_all(arr).expect("Cannot write frame to the output writer"),
+                }
+            }
+
+            fn clear(&mut self) 
+            {
+                match self {
+                    Sink::Console => clear_console(),
+                    Sink::Writer(w) => w.write_all(CLEAR_SEQUENCE).expect("Cannot write frame to the output writer"),
+                }
+            }
+
+            fn reset(&mut self) 
+            {
+                match self {
+                    Sink::Console => reset_console(),
+                    Sink::Writer(w) => w.write_all(RESET_SEQUENCE).expect("Cannot write frame to the output writer"),
+                }
+            }
+
+            fn flush(&mut self) 
+            {
+                match self {
+                    Sink::Console => flush_output(),
+                    Sink::Writer(w) => w.flush().expect("Cannot flush the output writer"),
+                }
+            }
+        }
+
+        impl Default for Renderer 
+        {
+            fn default() -> Renderer 
+            {
+                Renderer::new()
+            }
+        }
+
+        impl Renderer 
+        {
+            pub fn new() -> Renderer 
+            {
+                let mut r = Renderer {
+                    screen_dimensions: (Vec2 { x: (-1), y: (-1) }),
+                    swap_chain: (Vec::new()),
+                    force_redraw: true,
+                    transparent_char: TRANSPARENT_CHAR,
+                    color_mode: DEFAULT_COLOR_MODE,
+                    bold_colors: false,
+                    current_color: DEFAULT_COLOR,
+                    color_scratch: Vec::new(),
+                    auto_resize: true,
+                    sink: Sink::Console,
+                    max_cells: DEFAULT_MAX_CELLS,
+                    origin: Vec2 { x: 0, y: 0 },
+                    resized: false,
+                    recording: None,
+                    recording_error: None,
+                    frame_cell_width: DEFAULT_FRAME_CELL_WIDTH,
+                    frame_color: true,
+                    objects: Vec::new(),
+                    next_object_id: 0,
+                    cell_aspect: 2.,
+                    viewport: None,
+                };
+
+                r.swap_chain.push(FrameBuffer::new());
+                r.swap_chain.push(FrameBuffer::new());
+
+                r
+            }
+
+            // Fixed size renderer that never queries the console, 
+            // dimensions are in the same units as get_screen_dim()
+            pub fn with_dimensions(width: i16,
+                                   height: i16) -> Renderer 
+            {
+                let mut r = Renderer::new();
+                r.set_dimensions(width, height);
+
+                r
+            }
+
+            // Renders into writer instead of the console, pair it with set_dimensions() 
+            // where there's no console to take the size from
+            pub fn with_writer(writer: Box<dyn std::io::Write + Send>) -> Renderer 
+            {
+                let mut r = Renderer::new();
+                r.sink = Sink::Writer(writer);
+
+                r
+            }
+
+            // Locks the viewport to the given size, update() stops following the console
+            pub fn set_dimensions(&mut self,
+                                  width: i16,
+                                  height: i16) 
+            {
+                self.auto_resize = false;
+                self.apply_dimensions(Vec2 { x: width.max(0), y: height.max(0) });
+            }
+
+            // Leaves the console the way a shell expects it, blank with the cursor in the top left corner
+            pub fn shutdown(&mut self) 
+            {
+                #[cfg(feature = "headless")]
+                {
+                    if let Sink::Console = self.sink {
+                        return;
+                    }
+                }
+
+                self.sink.reset();
+                self.sink.flush();
+            }
+
+            // Caps the memory of the buffers, a console with more cells than this 
+            // gets a letterboxed viewport instead of filling the whole console
+            pub fn set_max_cells(&mut self,
+                                 max_cells: usize) 
+            {
+                self.max_cells = max_cells.max(1);
+            }
+
+            // Back to following the console size on every update()
+            pub fn set_auto_resize(&mut self) 
+            {
+                self.auto_resize = true;
+            }
+
+            pub fn draw_point_unnormalized(&mut self,
+                                           mut pos: Vec2<i32>,
+                                           ch: u8) 
+            {
+                if let Some(area) = self.viewport 
+                {
+                    if pos.x < 0 || pos.y < 0 || pos.x >= area.screen.x as i32 || pos.y >= area.screen.y as i32 / 2 {
+                        return;
+                    }
+
+                    pos = Vec2 { x: pos.x + area.origin.x, y: pos.y + area.origin.y };
+                }
+
+                let color = self.current_color;
+                self.get_back_screen().set(pos.x, pos.y, ch, color);
+            }
+
+            // Draws into size cells at top_left as if they were the whole screen, get_screen_dim() 
+            // and get_cell_dim() are the rectangle's and anything outside of it is clipped. For split 
+            // screens and pictures in pictures, render the game into each and render() the renderer 
+            // once they're gone. A viewport of a viewport is placed and clipped within it
+            pub fn viewport(&mut self,
+                            top_left: Vec2<i32>,
+                            size: Vec2<i32>) -> Viewport<'_> 
+            {
+                let bounds = self.get_cell_dim();
+                let offset = self.viewport.map_or(Vec2 { x: 0, y: 0 }, |area| area.origin);
+                let left = top_left.x.clamp(0, bounds.x as i32);
+                let top = top_left.y.clamp(0, bounds.y as i32);
+                let right = top_left.x.saturating_add(size.x.max(0)).clamp(left, bounds.x as i32);
+                let bottom = top_left.y.saturating_add(size.y.max(0)).clamp(top, bounds.y as i32);
+
+                let area = ViewportArea {
+                    origin: Vec2 { x: offset.x + left, y: offset.y + top },
+                    screen: Vec2 { x: (right - left) as i16, y: ((bottom - top) * 2) as i16 },
+                };
+
+                let previous = self.viewport.replace(area);
+                Viewport { renderer: self, previous }
+            }
+
+            // Text goes straight into terminal cells, pos isn't normalized like in draw_point
+            pub fn draw_string(&mut self,
+                               pos: Vec2<i32>,
+                               text: &str) 
+            {
+                for (i, ch) in text.bytes().enumerate() {
+                    self.draw_point_unnormalized(Vec2 { x: pos.x + i as i32, y: pos.y }, ch);
+                }
+            }
+
+            // Like draw_string(), but the transparent character leaves what's under it alone
+            pub fn draw_string_transparent(&mut self,
+                                           pos: Vec2<i32>,
+                                           text: &[u8]) 
+            {
+                for (i, ch) in text.iter().enumerate() 
+                {
+                    if *ch != self.transparent_char {
+                        self.draw_point_unnormalized(Vec2 { x: pos.x + i as i32, y: pos.y }, *ch);
+                    }
+                }
+            }
+
+            // Stamps a block of character art, src is row after row of src_width cells. 
+            // Cells off the screen are clipped, transparent ones leave the scene under them
+            pub fn blit(&mut self,
+                        top_left: Vec2<i32>,
+                        src: &[u8],
+                        src_width: i16) 
+            {
+                if src_width <= 0 {
+                    return;
+                }
+
+                for (y, row) in src.chunks(src_width as usize).enumerate() 
+                {
+                    self.draw_string_transparent(Vec2 { x: top_left.x, y: top_left.y + y as i32 }, row);
+                }
+            }
+
+            // Cells of overlays that the scene behind them shows through. Only a character terminals 
+            // don't print can be it, false leaves it as it was
+            pub fn set_transparent_char(&mut self, ch: u8) -> bool 
+            {
+                if !ch.is_ascii_control() {
+                    return false;
+                }
+
+                self.transparent_char = ch;
+                true
+            }
+
+            pub fn draw_point(&mut self,
+                              mut pos: Vec2<i32>,
+                              ch: u8) 
+            {
+                // Normialize
+                pos.y /= 2;
+
+                self.draw_point_unnormalized(pos, ch);
+            }
+
+            // Radius is in columns, the rows it covers follow the cell aspect, so the dot looks round. 
+            // Filled is a disk, otherwise a plus as wide and tall as the disk
+            pub fn draw_dot(&mut self,
+                            pos: Vec2<f32>,
+                            radius: f32,
+                            filled: bool,
+                            ch: u8) 
+            {
+                let center = Vec2 { x: pos.x.round() as i32, y: pos.y.round() as i32 };
+                let radius = radius.max(0.);
+                // Screen units are half rows
+                let radius_y = radius * 2. / self.cell_aspect;
+                let reach = Vec2 { x: radius.round() as i32, y: radius_y.round() as i32 };
+
+                for y in -reach.y..=reach.y 
+                {
+                    for x in -reach.x..=reach.x 
+                    {
+                        let inside = match filled {
+                            true => {
+                                let nx = x as f32 / radius.max(0.5);
+                                let ny = y as f32 / radius_y.max(0.5);
+                                nx * nx + ny * ny <= 1.
+                            }
+                            false => x == 0 || y == 0,
+                        };
+
+                        if inside {
+                            self.draw_point(Vec2 { x: center.x + x, y: center.y + y }, ch);
+                        }
+                    }
+                }
+            }
+
+            pub fn set_cell_aspect(&mut self, aspect: f32) 
+            {
+                self.cell_aspect = aspect.max(f32::EPSILON);
+            }
+
+            pub fn fill_rect(&mut self,
+                             top_left: Vec2<f32>,
+                             bottom_right: Vec2<f32>,
+                             ch: u8) 
+            {
+                for y in top_left.y as i32..bottom_right.y as i32 {
+                    for x in top_left.x as i32..bottom_right.x as i32 {
+                        self.draw_point(Vec2 { x, y }, ch);
+                    }
+                }
+            }
+
+            // Midpoint circle outline, cells off the screen are skipped by draw_point
+            pub fn draw_ring(&mut self,
+                             center: Vec2<f32>,
+                             radius: f32,
+                             ch: u8) 
+            {
+                let c = Vec2 { x: center.x.round() as i32, y: center.y.round() as i32 };
+                let mut x = radius.round() as i32;
+                let mut y = 0;
+                let mut error = 1 - x;
+
+                while x >= y 
+                {
+                    for (ox, oy) in [(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)] {
+                        self.draw_point(Vec2 { x: c.x + ox, y: c.y + oy }, ch);
+                    }
+
+                    y += 1;
+
+                    if error < 0 {
+                        error += 2 * y + 1;
+                    }
+                    else {
+                        x -= 1;
+                        error += 2 * (y - x) + 1;
+                    }
+                }
+            }
+
+            pub fn draw_line(&mut self,
+                             mut pos0: Vec2<f32>,
+                             mut pos1: Vec2<f32>,
+                             ch: u8) 
+            {
+                let mut steep = false;
+
+                if (pos0.x - pos1.x).abs() < (pos0.y - pos1.y).abs() {
+                    swap(&mut pos0.x, &mut pos0.y);
+                    swap(&mut pos1.x, &mut pos1.y);
+                    steep = true
+                }
+
+                if pos0.x > pos1.x {
+                    swap(&mut pos0.x, &mut pos1.x);
+                    swap(&mut pos0.y, &mut pos1.y);
+                }
+
+                let dx = pos1.x - pos0.x;
+                let dy = pos1.y - pos0.y;
+                let derror: f32 = (dy / dx).abs();
+                let mut error: f32 = 0.0;
+                let mut y = pos0.y as i32;
+
+                for x in pos0.x as i32..pos1.x as i32 
+                {
+                    if steep {
+                        self.draw_point(Vec2 { x: (y), y: (x) }, ch);
+                    }
+                    else {
+                        self.draw_point(Vec2 { x: (x), y: (y) }, ch);
+                    }
+
+                    error += derror;
+                    if error > 0.5 {
+                        if pos1.y > pos0.y {
+                            y += 1;
+                        }
+                        else {
+                            y -= 1;
+                        }
+
+                        error -= 1.0;
+                    }
+                }
+            }
+
+            // Xiaolin Wu's line, coverage of every cell picks a character from the ramp. 
+            // Goes by cells, two half rows in one cell would be one pair overwriting the other
+            pub fn draw_line_aa(&mut self,
+                                mut pos0: Vec2<f32>,
+                                mut pos1: Vec2<f32>,
+                                chars: &[u8]) 
+            {
+                if chars.is_empty() {
+                    return;
+                }
+
+                pos0.y /= 2.;
+                pos1.y /= 2.;
+
+                let steep = (pos1.y - pos0.y).abs() > (pos1.x - pos0.x).abs();
+
+                if steep {
+                    swap(&mut pos0.x, &mut pos0.y);
+                    swap(&mut pos1.x, &mut pos1.y);
+                }
+
+                if pos0.x > pos1.x {
+                    swap(&mut pos0, &mut pos1);
+                }
+
+                let dx = pos1.x - pos0.x;
+                let dy = pos1.y - pos0.y;
+                let gradient = if dx == 0. { 1. } else { dy / dx };
+
+                for x in pos0.x.round() as i32..=pos1.x.round() as i32 
+                {
+                    let y = pos0.y + gradient * (x as f32 - pos0.x);
+                    let y_floor = y.floor();
+                    let coverage = y - y_floor;
+
+                    self.plot_coverage(x, y_floor as i32, 1. - coverage, steep, chars);
+                    self.plot_coverage(x, y_floor as i32 + 1, coverage, steep, chars);
+                }
+            }
+
+            fn plot_coverage(&mut self,
+                             x: i32,
+                             y: i32,
+                             coverage: f32,
+                             steep: bool,
+                             chars: &[u8]) 
+            {
+                if coverage <= 0. {
+                    return;
+                }
+
+                let index = ((coverage * chars.len() as f32) as usize).min(chars.len() - 1);
+
+                if steep {
+                    self.draw_point_unnormalized(Vec2 { x: (y), y: (x) }, chars[index]);
+                }
+                else {
+                    self.draw_point_unnormalized(Vec2 { x: (x), y: (y) }, chars[index]);
+                }
+            }
+
+            // Diagnostic picture independent of the game, to check cursor positioning, 
+            // colors and the aspect ratio of a terminal
+            pub fn draw_test_pattern(&mut self) 
+            {
+                let w = self.get_screen_dim().x as f32;
+                let h = self.get_screen_dim().y as f32;
+                let cells = self.get_cell_dim();
+
+                // Border, one cell thick
+                self.fill_rect(Vec2 { x: 0., y: 0. }, Vec2 { x: w, y: 2. }, WALL_2D_CHAR);
+                self.fill_rect(Vec2 { x: 0., y: h - 2. }, Vec2 { x: w, y: h }, WALL_2D_CHAR);
+                self.fill_rect(Vec2 { x: 0., y: 0. }, Vec2 { x: 1., y: h }, WALL_2D_CHAR);
+                self.fill_rect(Vec2 { x: w - 1., y: 0. }, Vec2 { x: w, y: h }, WALL_2D_CHAR);
+
+                self.draw_line(Vec2 { x: 1., y: 2. }, Vec2 { x: w - 1., y: h - 2. }, b'\\');
+                self.draw_line(Vec2 { x: w - 1., y: 2. }, Vec2 { x: 1., y: h - 2. }, b'/');
+
+                // Ramp from the darkest to the brightest character across the inside of the border
+                let ramp_row = cells.y as i32 / 2 + 1;
+                for x in 1..(cells.x as i32 - 1) 
+                {
+                    let index = (x - 1) as usize * SHADING_RAMP.len() / (cells.x as usize - 2).max(1);
+                    self.draw_point_unnormalized(Vec2 { x, y: ramp_row }, SHADING_RAMP[index]);
+                }
+
+                let text = format!(" {}x{} ", cells.x, cells.y);
+                self.draw_string(Vec2 { 
+                                     x: (cells.x as i32 - text.len() as i32) / 2,
+                                     y: cells.y as i32 / 2 - 1,
+                                 },
+                                 &text);
+            }
+
+            pub fn update(&mut self) 
+            {
+                self.resize();
+                self.clear_whole_screen();
+            }
+
+            pub fn add_object(&mut self,
+                              object: Box<dyn Drawable + Send>) -> ObjectId 
+            {
+                let id = ObjectId(self.next_object_id);
+                self.next_object_id += 1;
+                self.objects.push((id, object));
+
+                id
+            }
+
+            // False when there's no object with that id, it was removed already
+            pub fn remove_object(&mut self,
+                                 id: ObjectId) -> bool 
+            {
+                let count = self.objects.len();
+                self.objects.retain(|(object_id, _)| *object_id != id);
+
+                self.objects.len() != count
+            }
+
+            fn update_objs(&mut self) 
+            {
+                // Out of self while drawing, the objects draw into it. Objects added meanwhile go last
+                let mut objects = std::mem::take(&mut self.objects);
+
+                for (_, object) in objects.iter() 
+                {
+                    object.draw(self);
+                }
+
+                objects.append(&mut self.objects);
+                self.objects = objects;
+            }
+
+            pub fn render(&mut self) 
+            {
+                self.update_objs();
+                self.swap_screens();
+                self.render_frame();
+                self.resized = false;
+                self.record_frame();
+            }
+
+            // From the next render() on, every frame goes to dir as frame_00001.ppm, frame_00002.ppm 
+            // and so on until max_frames of them are written. Err when dir can't be created
+            pub fn begin_recording(&mut self,
+                                   dir: &str,
+                                   max_frames: u32) -> std::io::Result<()> 
+            {
+                std::fs::create_dir_all(dir)?;
+
+                self.recording = Some(FrameRecording { dir: dir.into(), written: 0, max_frames });
+                self.recording_error = None;
+                Ok(())
+            }
+
+            pub fn is_recording(&self) -> bool 
+            {
+                self.recording.is_some()
+            }
+
+            // Set when a frame couldn't be written, that ends the recording instead of the game
+            pub fn recording_error(&self) -> Option<&str> 
+            {
+                self.recording_error.as_deref()
+            }
+
+            // Pixels across a cell in recorded frames, and whether they're in the cells' colors or gray
+            pub fn set_frame_format(&mut self,
+                                    cell_width: usize,
+                                    color: bool) 
+            {
+                self.frame_cell_width = cell_width.max(1);
+                self.frame_color = color;
+            }
+
+            fn record_frame(&mut self) 
+            {
+                let recording = match self.recording.as_mut() {
+                    Some(recording) => recording,
+                    None => return,
+                };
+
+                recording.written += 1;
+                let path = recording.dir.join(format!("frame_{:05}.ppm", recording.written));
+                let done = recording.written >= recording.max_frames;
+
+                let result = std::fs::File::create(&path)
+                    .map(std::io::BufWriter::new)
+                    .and_then(|mut file| {
+                        self.swap_chain[FRONT_INDEX].write_ppm(&mut file, self.frame_cell_width, self.frame_color)?;
+                        std::io::Write::flush(&mut file)
+                    });
+
+                if let Err(err) = result {
+                    self.recording_error = Some(format!("Cannot write {}: {}", path.display(), err));
+                    self.recording = None;
+                } else if done {
+                    self.recording = None;
+                }
+            }
+
+            // True from the update() or set_dimensions() that changed the viewport's size 
+            // until the end of the next render(), which repaints the whole console
+            pub fn was_resized(&self) -> bool 
+            {
+                self.resized
+            }
+
+            pub fn get_screen_dim(&self) -> &Vec2<i16> 
+            {
+                match &self.viewport {
+                    Some(area) => &area.screen,
+                    None => &self.screen_dimensions,
+                }
+            }
+
+            pub fn set_color(&mut self, color: Color) 
+            {
+                self.current_color = color;
+            }
+
+            pub fn get_color_mode(&self) -> ColorMode 
+            {
+                self.color_mode
+            }
+
+            pub fn set_color_mode(&mut self, mode: ColorMode) 
+            {
+                if mode != self.color_mode 
+                {
+                    self.color_mode = mode;
+                    self.request_full_redraw();
+                }
+            }
+
+            pub fn set_bold_colors(&mut self, bold: bool) 
+            {
+                if bold != self.bold_colors 
+                {
+                    self.bold_colors = bold;
+                    self.request_full_redraw();
+                }
+            }
+
+            pub fn apply_preset(&mut self, preset: RenderPreset) 
+            {
+                self.set_bold_colors(preset.bold_colors());
+            }
+
+            // Size in terminal cells, the space draw_string() works in
+            pub fn get_cell_dim(&self) -> Vec2<i16> 
+            {
+                let screen = self.get_screen_dim();
+
+                Vec2 { x: screen.x, y: screen.y / 2 }
+            }
+
+            // Next render_frame() ignores the diff and repaints every cell,
+            // use it when the console got out of sync with the swap chain
+            pub fn request_full_redraw(&mut self) 
+            {
+                self.force_redraw = true;
+            }
+
+            // Copy of the frame that's on the screen, characters and colors, for debugging
+            pub fn snapshot(&self) -> FrameBuffer 
+            {
+                self.swap_chain[FRONT_INDEX].clone()
+            }
+
+            // Puts a snapshot back, the next render() repaints it in full. 
+            // False when the viewport got resized since the snapshot was taken
+            pub fn restore(&mut self, snapshot: &FrameBuffer) -> bool 
+            {
+                let back = self.get_back_screen();
+
+                if back.width != snapshot.width || back.height != snapshot.height {
+                    return false;
+                }
+
+                back.data.copy_from_slice(&snapshot.data);
+                back.colors.copy_from_slice(&snapshot.colors);
+                self.request_full_redraw();
+
+                true
+            }
+
+            #[inline]
+            fn get_back_screen(&mut self) -> &mut FrameBuffer 
+            {
+                &mut self.swap_chain[BACK_INDEX]
+            }
+
+            fn resize(&mut self) 
+            {
+                if !self.auto_resize {
+                    return;
+                }
+
+                #[cfg(not(feature = "headless"))]
+                let console = get_dimensions();
+
+                #[cfg(feature = "headless")]
+                let console = HEADLESS_DIMENSIONS;
+
+                let viewport = fit_to_cells(console, self.max_cells);
+                let origin = Vec2 { 
+                    x: (console.x - viewport.x) / 2,
+                    y: (console.y - viewport.y) / 4,
+                };
+
+                if origin.x != self.origin.x || origin.y != self.origin.y 
+                {
+                    self.origin = origin;
+                    self.request_full_redraw();
+                }
+
+                self.apply_dimensions(viewport);
+            }
+
+            fn apply_dimensions(&mut self,
+                                dimensions: Vec2<i16>) 
+            {
+                if dimensions.x != self.screen_dimensions.x || dimensions.y != self.screen_dimensions.y 
+                {
+                    self.resized = true;
+                    self.request_full_redraw();
+                }
+
+                self.screen_dimensions = dimensions;
+
+                // Buffers hold terminal cells, every cell is two units of screen_dimensions tall
+                let d = Vec2 { x: dimensions.x, y: dimensions.y / 2 };
+
+                if self.swap_chain.iter().any(|screen| screen.width() != d.x || screen.height() != d.y) 
+                {
+                    // Old contents don't map onto the new layout, so start both buffers blank
+                    // and let the next render_frame() repaint every cell
+                    for screen in self.swap_chain.iter_mut() {
+                        screen.resize(d.x, d.y, CHAR_EMPTY);
+                    }
+
+                    self.request_full_redraw();
+                }
+            }
+
+            #[inline]
+            fn clear_whole_screen(&mut self) 
+            {
+                self.get_back_screen().fill(CHAR_EMPTY);
+            }
+
+            #[inline]
+            fn swap_screens(&mut self) 
+            {
+                self.swap_chain.swap(FRONT_INDEX, BACK_INDEX);
+            }
+
+            fn render_frame(&mut self) 
+            {
+                let mut anchor: Option<usize> = None;
+    
+                let origin = self.origin;
+
+                // Headless builds keep quiet on the console, an injected writer still gets the frames
+                #[cfg(feature = "headless")]
+                {
+                    if let Sink::Console = self.sink {
+                        return;
+                    }
+                }
+
+                self.sink.set_cursor_position(origin, Vec2 
+                    { 
+                        x: 0,
+                        y: 0,
+                    });
+
+                if self.force_redraw 
+                {
+                    self.force_redraw = false;
+                    self.render_whole_frame();
+                    return;
+                }
+
+                let front = &self.swap_chain[FRONT_INDEX];
+                let back = &self.swap_chain[BACK_INDEX];
+                let color_mode = self.color_mode;
+                let compare_colors = color_mode != ColorMode::Monochrome;
+
+                // Walks both buffers side by side instead of indexing them cell by cell
+                let changes = front.data().iter().zip(back.data())
+                    .zip(front.colors().iter().zip(back.colors()))
+                    .map(|((f, b), (fc, bc))| f != b || (compare_colors && fc != bc));
+
+                for (i, changed) in changes.enumerate() 
+                {
+                    match (anchor, changed) 
+                    {
+                        (None, true) => anchor = Some(i),
+                        (Some(start), false) => {
+                            self.sink.set_cursor_position(origin, front.coords(start));
+
+                            output_cells(&mut self.sink, front, start..i, color_mode, self.bold_colors, &mut self.color_scratch);
+
+                            self.sink.set_cursor_position(origin, Vec2 { 
+                                x: 0,
+                                y: 0,
+                            });
+
+                            anchor = None;
+                        }
+                        _ => {}
+                    }
+                }
+
+                if let Some(anchor) = anchor 
+                {
+                    self.sink.set_cursor_position(origin, front.coords(anchor));
+                    output_cells(&mut self.sink, front, anchor..front.len() - 1, color_mode, self.bold_colors, &mut self.color_scratch);
+                }
+
+                self.sink.set_cursor_position(origin, Vec2 { 
+                    x: 0,
+                    y: 0,
+                });
+                self.sink.flush();
+
+                // println!("{} {}", self.get_screen_dim().x, self.get_screen_dim().y);
+            }
+
+            fn render_whole_frame(&mut self) 
+            {
+                let origin = self.origin;
+                let front = &self.swap_chain[FRONT_INDEX];
+
+                // Whatever was printed around a letterboxed viewport has to go, 
+                // and so does whatever the terminal reflowed when it got resized
+                if origin.x != 0 || origin.y != 0 || self.resized {
+                    self.sink.clear();
+                }
+
+                if front.is_empty() {
+                    return;
+                }
+
+                // Row by row, skipping the very last cell like the diff path does
+                for y in 0..front.height() as i32 
+                {
+                    let start = front.index(0, y).unwrap();
+                    let mut width = front.width() as usize;
+
+                    if start + width == front.len() {
+                        width -= 1;
+                    }
+
+                    self.sink.set_cursor_position(origin, Vec2 { 
+                        x: 0,
+                        y: y as i16,
+                    });
+
+                    output_cells(&mut self.sink, front, start..start + width, self.color_mode, self.bold_colors, &mut self.color_scratch);
+                }
+
+                self.sink.set_cursor_position(origin, Vec2 { 
+                    x: 0,
+                    y: 0,
+                });
+                self.sink.flush();
+            }
+        }
+        
+        // Writes a run of cells, in the color modes every run starts with its color and ends with 
+        // a reset, so nothing else printed to the console inherits it
+        fn output_cells(sink: &mut Sink,
+                        screen: &FrameBuffer,
+                        range: std::ops::Range<usize>,
+                        color_mode: ColorMode,
+                        bold: bool,
+                        scratch: &mut Vec<u8>) 
+        {
+            use std::io::Write;
+
+            // The transparent character only means something to blit() and draw_string_transparent(), 
+            // one drawn with anything else is an empty cell. Nothing else that isn't printed belongs 
+            // on the screen either
+            let printable = |ch: u8| if ch.is_ascii_control() { CHAR_EMPTY } else { ch };
+
+            scratch.clear();
+
+            if color_mode == ColorMode::Monochrome 
+            {
+                scratch.extend(screen.data()[range].iter().map(|ch| printable(*ch)));
+                sink.output_array(scratch);
+                return;
+            }
+
+            if bold {
+                scratch.extend_from_slice(b"\x1B[1m");
+            }
+
+            for i in range.clone() 
+            {
+                let color = screen.colors()[i];
+
+                if i == range.start || color != screen.colors()[i - 1] 
+                {
+                    let _ = match color_mode {
+                        ColorMode::TrueColor => write!(scratch, "\x1B[38;2;{};{};{}m", color.r, color.g, color.b),
+                        _ => write!(scratch, "\x1B[38;5;{}m", color.to_ansi256()),
+                    };
+                }
+
+                scratch.push(printable(screen.data()[i]));
+            }
+
+            scratch.extend_from_slice(b"\x1B[0m");
+            sink.output_array(scratch);
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        compile_error!("terminal::output supports only unix and windows targets");
+
+        #[cfg(unix)]
+        pub fn get_dimensions() -> Vec2<i16> 
+        {
+            use nix::libc::{winsize, ioctl, STDOUT_FILENO, TIOCGWINSZ};
+
+            let mut win: winsize = winsize { ws_row: (0), 
+                                             ws_col: (0),
+                                             ws_xpixel: (0),
+                                             ws_ypixel: (0) };
+
+            unsafe {
+                if ioctl(STDOUT_FILENO, TIOCGWINSZ, &mut win as *mut winsize) != 0 
+                {
+                    panic!("Cannot get terminal size with ioctl(TIOCGWINSZ), errno returned {err}", 
+                           err = std::io::Error::last_os_error());
+                }
+            }
+
+            // Rows are doubled the same way as on windows, every cell is two "pixels" tall
+            Vec2 { x: win.ws_col as i16, y: win.ws_row as i16 * 2 }
+        }
+
+        // How much of a cell a character covers, from 0 for empty up to 1, for turning frames into images. 
+        // The shading ramp goes from sparse to dense, anything else visible counts as half covered
+        fn ink_density(ch: u8) -> f32 
+        {
+            if ch == CHAR_EMPTY || ch == TRANSPARENT_CHAR {
+                return 0.;
+            }
+
+            match SHADING_RAMP.iter().position(|&c| c == ch) {
+                Some(i) => (i + 1) as f32 / SHADING_RAMP.len() as f32,
+                None => 0.5,
+            }
+        }
+
+        // Largest viewport of the same aspect as dimensions that has at most max_cells cells, 
+        // drawing outside of it is dropped by FrameBuffer::index() like anything else off the buffer
+        fn fit_to_cells(dimensions: Vec2<i16>,
+                        max_cells: usize) -> Vec2<i16> 
+        {
+            let cells = dimensions.x.max(0) as usize * (dimensions.y.max(0) / 2) as usize;
+
+            if cells <= max_cells {
+                return dimensions;
+            }
+
+            let factor = (max_cells as f32 / cells as f32).sqrt();
+
+            Vec2 { 
+                x: ((dimensions.x as f32 * factor) as i16).max(1),
+                y: ((dimensions.y as f32 / 2. * factor) as i16).max(1) * 2,
+            }
+        }
+
+        // ANSI cursor positions are 1-based, ours are 0-based like winapi's COORD
+        fn write_ansi_cursor_position(w: &mut dyn std::io::Write, 
+                                      dim: Vec2<i16>) -> std::io::Result<()> 
+        {
+            write!(w, "\x1B[{};{}H", dim.y as i32 + 1, dim.x as i32 + 1)
+        }
+
+        #[cfg(unix)]
+        pub fn set_cursor_position(dim: Vec2<i16>) 
+        {
+            use std::io::stdout;
+
+            write_ansi_cursor_position(&mut stdout().lock(), dim)
+                .expect("Cannot write cursor position to stdout");
+        }
+
+        #[cfg(unix)]
+        pub fn output_array(arr: &[u8]) 
+        {
+            use std::io::{stdout, Write};
+
+            stdout().lock().write_all(arr).expect("Cannot write frame to stdout");
+        }
+
+        #[cfg(unix)]
+        fn clear_console() 
+        {
+            output_array(CLEAR_SEQUENCE);
+        }
+
+        #[cfg(unix)]
+        fn reset_console() 
+        {
+            output_array(RESET_SEQUENCE);
+        }
+
+        #[cfg(unix)]
+        pub fn flush_output() 
+        {
+            use std::io::{stdout, Write};
+
+            stdout().lock().flush().expect("Cannot flush stdout");
+        }
+
+        #[cfg(windows)]
+        pub fn get_dimensions() -> Vec2<i16> 
+        {
+            use winapi::um::processenv::GetStdHandle;
+            use winapi::um::wincon::GetConsoleScreenBufferInfo;
+            use winapi::um::wincon::CONSOLE_SCREEN_BUFFER_INFO;
+            use winapi::um::wincon::SMALL_RECT;
+            use winapi::um::wincon::COORD;
+
+            let mut csbi = CONSOLE_SCREEN_BUFFER_INFO {
+                dwSize: COORD { X: (-1), Y: (-1) },
+                dwCursorPosition: COORD { X: (-1), Y: (-1) },
+                wAttributes: -1_i16 as u16,
+                srWindow: SMALL_RECT { 
+                    Left: (-1), 
+                    Top: (-1), 
+                    Right: (-1), 
+                    Bottom: (-1) },
+                    dwMaximumWindowSize: COORD { X: (-1), Y: (-1) },
+            };
+    
+            unsafe { 
+                if GetConsoleScreenBufferInfo(GetStdHandle(STD_OUTPUT), &mut csbi) == 0 
+                {
+                    panic!("Cannot get console info in winapi, GetLastError() returned {err_code}", 
+                           err_code = crate::windows_errors::get_last_error());
+                }
+            }
+
+            Vec2 { x: csbi.dwSize.X, y: csbi.dwSize.Y * 2 }
+        }
+
+        #[cfg(windows)]
+        const STD_OUTPUT: u32 = -11_i32 as u32;
+
+        #[cfg(windows)]
+        fn set_cursor_position(dim: Vec2<i16>) 
+        {
+            use winapi::um::processenv::GetStdHandle; 
+            use winapi::um::wincon::SetConsoleCursorPosition;
+            use winapi::um::wincon::COORD;
+
+            unsafe { 
+                if SetConsoleCursorPosition(GetStdHandle(STD_OUTPUT), COORD { X: (dim.x), Y: (dim.y) }) == 0 
+                {
+                    panic!("Cannot set cursor positon in winapi, GetLastError() returned {err_code}", 
+                           err_code = crate::windows_errors::get_last_error());
+                }
+            }
+        }
+
+        #[cfg(windows)]
+        fn output_array(arr: &[u8]) 
+        {
+            use winapi::ctypes::c_void;
+            use winapi::um::consoleapi::WriteConsoleA;
+            use winapi::um::processenv::GetStdHandle;
+
+            unsafe {
+                if WriteConsoleA(
+                    GetStdHandle(STD_OUTPUT), 
+                    arr.as_ptr() as *const c_void,
+                    arr.len() as u32,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut()) == 0 
+                {
+                    panic!("Cannot set cursor positon in winapi, GetLastError() returned {err_code}", 
+                           err_code = crate::windows_errors::get_last_error());
+                }
+            }
+        }
+
+        // Blanks the console with spaces, there are no escape codes to do it with
+        #[cfg(windows)]
+        fn reset_console() 
+        {
+            let d = get_dimensions();
+
+            set_cursor_position(Vec2 { x: 0, y: 0 });
+            output_array(&vec![CHAR_EMPTY; d.x.max(0) as usize * (d.y.max(0) / 2) as usize]);
+            set_cursor_position(Vec2 { x: 0, y: 0 });
+        }
+
+        // WriteConsoleA doesn't understand escape codes, so around a letterboxed viewport 
+        // the old output stays until something else overwrites it
+        #[cfg(windows)]
+        fn clear_console() 
+        {
+        }
+
+        // WriteConsoleA isn't buffered
+        #[cfg(windows)]
+        fn flush_output() 
+        {
+        }
+
+        #[cfg(test)]
+        mod tests 
+        {
+            use super::*;
+
+            // Off screen, width cells across and rows down
+            fn renderer(width: i16,
+                        rows: i16) -> Renderer 
+            {
+                let mut output = Renderer::with_writer(Box::new(std::io::sink()));
+                output.set_dimensions(width, rows * 2);
+                output.update();
+                output
+            }
+
+            // The frame on the screen, one string per row
+            fn rows(output: &Renderer) -> Vec<String> 
+            {
+                let frame = output.snapshot();
+
+                frame.data()
+                    .chunks(frame.width() as usize)
+                    .map(|row| String::from_utf8_lossy(row).into_owned())
+                    .collect()
+            }
+
+            struct Marker;
+
+            impl Drawable for Marker 
+            {
+                fn draw(&self, output: &mut Renderer) 
+                {
+                    output.draw_point_unnormalized(Vec2 { x: 1, y: 1 }, b'Z');
+                }
+            }
+
+            #[test]
+            fn objects_are_drawn_over_every_frame() 
+            {
+                let mut output = renderer(4, 3);
+                let id = output.add_object(Box::new(Marker));
+
+                for _ in 0..3 
+                {
+                    output.update();
+                    for y in 0..3 
+                    {
+                        for x in 0..4 {
+                            output.draw_point_unnormalized(Vec2 { x, y }, b'#');
+                        }
+                    }
+                    output.render();
+
+                    assert_eq!(rows(&output), ["####", "#Z##", "####"]);
+                }
+
+                assert!(output.remove_object(id));
+                assert!(!output.remove_object(id));
+                output.update();
+                output.render();
+                assert_eq!(rows(&output)[1], "    ");
+            }
+
+            // Keeps what the renderer writes, to look at after it's gone
+            #[derive(Clone, Default)]
+            struct Written(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+            impl std::io::Write for Written 
+            {
+                fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> 
+                {
+                    self.0.lock().unwrap().extend_from_slice(buf);
+                    Ok(buf.len())
+                }
+
+                fn flush(&mut self) -> std::io::Result<()> 
+                {
+                    Ok(())
+                }
+            }
+
+            #[test]
+            fn blit_leaves_transparent_cells_alone() 
+            {
+                let mut output = renderer(4, 2);
+                for x in 0..4 {
+                    output.draw_point_unnormalized(Vec2 { x, y: 1 }, b'#');
+                }
+
+                output.blit(Vec2 { x: 1, y: 0 }, &[b'a', TRANSPARENT_CHAR, b'b', TRANSPARENT_CHAR, b'c', b'd'], 3);
+                output.swap_screens();
+
+                assert_eq!(rows(&output), [" a b", "##cd"]);
+            }
+
+            // The restored frame comes back with its colors and is repainted whole, 
+            // a snapshot of another size doesn't fit anymore
+            #[test]
+            fn snapshot_restores_a_drawn_frame() 
+            {
+                let mut output = renderer(5, 2);
+                output.set_color(Color { r: 200, g: 10, b: 10 });
+                output.draw_string(Vec2 { x: 0, y: 0 }, "scene");
+                output.render();
+                let saved = output.snapshot();
+
+                output.update();
+                output.draw_string(Vec2 { x: 0, y: 1 }, "other");
+                output.render();
+                assert_eq!(rows(&output), ["     ", "other"]);
+
+                output.update();
+                assert!(output.restore(&saved));
+                assert!(output.force_redraw);
+                output.render();
+
+                assert_eq!(rows(&output), ["scene", "     "]);
+                assert_eq!(output.snapshot().colors(), saved.colors());
+
+                output.set_dimensions(6, 4);
+                assert!(!output.restore(&saved));
+            }
+
+            // The radius is in screen units, 4 columns across and 4 half rows down. Every row 
+            // mirrors around the center column, rows pair up half rows so top and bottom can't mirror
+            #[test]
+            fn ring_is_a_symmetric_outline() 
+            {
+                let mut output = renderer(11, 6);
+                output.draw_ring(Vec2 { x: 5., y: 6. }, 4., b'o');
+                output.swap_screens();
+
+                let ring = rows(&output);
+                assert_eq!(ring, ["           ", 
+                                  "  ooooooo  ", 
+                                  " oo     oo ", 
+                                  " o       o ", 
+                                  "  oo   oo  ", 
+                                  "    ooo    "]);
+                assert!(ring.iter().all(|row| row.chars().rev().collect::<String>() == *row));
+
+                // Half off the screen it's clipped, nothing wraps onto other rows
+                let mut output = renderer(11, 6);
+                output.draw_ring(Vec2 { x: 0., y: 6. }, 4., b'o');
+                output.swap_screens();
+                assert_eq!(rows(&output)[1..], ["oooo       ", 
+                                                "   oo      ", 
+                                                "    o      ", 
+                                                "  oo       ", 
+                                                "oo         "]);
+            }
+
+            // What hangs over an edge is cut off, nothing wraps around onto the next row
+            #[test]
+            fn blit_clips_at_the_edges() 
+            {
+                let art = b"abcdef";
+
+                let mut output = renderer(4, 3);
+                output.blit(Vec2 { x: 2, y: 0 }, art, 3);
+                output.blit(Vec2 { x: -1, y: 2 }, art, 3);
+                output.swap_screens();
+
+                assert_eq!(rows(&output), ["  ab", 
+                                           "  de", 
+                                           "bc  "]);
+            }
+
+            #[test]
+            fn transparent_char_is_never_written_out() 
+            {
+                let written = Written::default();
+                let mut output = Renderer::with_writer(Box::new(written.clone()));
+                output.set_dimensions(4, 2);
+                output.update();
+
+                assert!(!output.set_transparent_char(b'x'));
+                assert!(output.set_transparent_char(0x7f));
+
+                output.draw_string(Vec2 { x: 0, y: 0 }, "a\x7f\x00");
+                output.render();
+
+                let written = written.0.lock().unwrap();
+                assert!(written.windows(3).any(|cells| cells == b"a  "), "{:?}", written);
+                assert!(!written.iter().any(|ch| *ch == 0x7f || *ch == 0));
+            }
+
+            // Both buffers take the new size blank, smaller or bigger, and the frame after it is 
+            // written out whole instead of diffed against the old one
+            #[test]
+            fn resize_shrinks_and_grows_both_buffers_blank() 
+            {
+                let mut output = renderer(6, 3);
+                output.draw_string(Vec2 { x: 0, y: 0 }, "abcdef");
+                output.render();
+                assert_eq!(rows(&output)[0], "abcdef");
+
+                for (width, height) in [(3, 2), (8, 4)] 
+                {
+                    output.set_dimensions(width, height * 2);
+
+                    for screen in output.swap_chain.iter() 
+                    {
+                        assert_eq!((screen.width(), screen.height()), (width, height));
+                        assert_eq!(screen.len(), width as usize * height as usize);
+                        assert_eq!(screen.colors().len(), screen.len());
+                        assert!(screen.data().iter().all(|&ch| ch == CHAR_EMPTY));
+                    }
+                    assert!(output.force_redraw);
+
+                    output.update();
+                    output.draw_string(Vec2 { x: 0, y: 0 }, "ab");
+                    output.render();
+                    assert!(!output.force_redraw);
+                    assert_eq!(rows(&output)[0], format!("{:<1$}", "ab", width as usize));
+                }
+            }
+
+            // A shallow line drops one row every four columns, the ramp fades from one row to the next
+            #[test]
+            fn aa_line_covers_the_plain_line() 
+            {
+                let (from, to) = (Vec2 { x: 0., y: 0. }, Vec2 { x: 8., y: 4. });
+
+                let mut plain = renderer(9, 3);
+                plain.draw_line(from, to, b'#');
+                plain.swap_screens();
+
+                let mut aa = renderer(9, 3);
+                aa.draw_line_aa(from, to, b".-=#");
+                aa.swap_screens();
+
+                assert_eq!(rows(&aa), ["##=-     ", 
+                                       " -=###=- ", 
+                                       "     -=##"]);
+
+                for (plain_row, aa_row) in rows(&plain).iter().zip(rows(&aa).iter()) 
+                {
+                    for (plain_ch, aa_ch) in plain_row.chars().zip(aa_row.chars()) {
+                        assert!(plain_ch == ' ' || aa_ch != ' ', "{:?}\n{:?}", rows(&plain), rows(&aa));
+                    }
+                }
+            }
+
+            // Rows as wide as an i16 goes, and a buffer whose data fell out of step with its size
+            #[test]
+            fn frame_buffer_index_at_large_dimensions() 
+            {
+                let mut buffer = FrameBuffer::new();
+                buffer.resize(i16::MAX, 3, b' ');
+
+                assert_eq!(buffer.index(i16::MAX as i32 - 1, 2), Some(3 * i16::MAX as usize - 1));
+                assert_eq!(buffer.index(i16::MAX as i32, 0), None);
+                assert_eq!(buffer.index(0, 3), None);
+                assert_eq!(buffer.index(i32::MAX, i32::MAX), None);
+                assert_eq!(buffer.index(-1, 0), None);
+
+                let mut out_of_step = FrameBuffer { 
+                    data: vec![b' '; 10], 
+                    colors: vec![DEFAULT_COLOR; 10], 
+                    width: i16::MAX, 
+                    height: i16::MAX,
+                };
+                assert_eq!(out_of_step.index(9, 0), Some(9));
+                assert_eq!(out_of_step.index(10, 0), None);
+                assert_eq!(out_of_step.index(i16::MAX as i32 - 1, i16::MAX as i32 - 1), None);
+                out_of_step.set(0, 1, b'#', DEFAULT_COLOR);
+                assert!(out_of_step.data().iter().all(|&ch| ch == b' '));
+            }
+
+            // Two half rows make a row, taller cells squash the dot to fewer of them
+            #[test]
+            fn dots_are_round_for_the_cell_aspect() 
+            {
+                let dot = |filled: bool, aspect: f32| {
+                    let mut output = renderer(7, 5);
+                    output.set_cell_aspect(aspect);
+                    output.draw_dot(Vec2 { x: 3., y: 4. }, 2., filled, b'#');
+                    output.render();
+                    rows(&output)
+                };
+
+                assert_eq!(dot(true, 2.), ["       ", 
+                                           "  ###  ", 
+                                           " ##### ", 
+                                           "   #   ", 
+                                           "       "]);
+                assert_eq!(dot(false, 2.), ["       ", 
+                                            "   #   ", 
+                                            " ##### ", 
+                                            "   #   ", 
+                                            "       "]);
+                assert_eq!(dot(true, 4.), ["       ", 
+                                           "   #   ", 
+                                           " ##### ", 
+                                           "       ", 
+                                           "       "]);
+            }
+        }
+    }
+
+    pub mod input 
+    {
+        use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::sync::atomic;
+        #[cfg(windows)]
+        use std::ptr::null_mut;
+        use std::thread::spawn;
+        use std::time::{Duration, Instant};
+        #[cfg(unix)]
+        use nix::libc::termios;
+
+        pub mod keys 
+        {
+            #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+            pub enum Key 
+            {
+                None,
+                W,
+                A,
+                S,
+                D,
+                Q,
+                E,
+                X,
+                ArrowLeft,
+                ArrowUp,
+                ArrowRight,
+                ArrowDown,
+                Space,
+                P,
+                N,
+                R,
+                F,
+                L,
+                M,
+                T,
+                C,
+                // Ctrl+c in a raw mode terminal
+                Interrupt,
+            }
+
+            // Same order as the enum, so a key can round trip through an atomic as its index
+            const ALL_KEYS: [Key; 22] = [
+                Key::None,
+                Key::W,
+                Key::A,
+                Key::S,
+                Key::D,
+                Key::Q,
+                Key::E,
+                Key::X,
+                Key::ArrowLeft,
+                Key::ArrowUp,
+                Key::ArrowRight,
+                Key::ArrowDown,
+                Key::Space,
+                Key::P,
+                Key::N,
+                Key::R,
+                Key::F,
+                Key::L,
+                Key::M,
+                Key::T,
+                Key::C,
+                Key::Interrupt,
+            ];
+
+            // Every key that's down at once, one bit per key in the enum's order
+            #[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+            pub struct KeyState 
+            {
+                bits: u32,
+            }
+
+            impl KeyState 
+            {
+                pub fn from_bits(bits: u32) -> KeyState 
+                {
+                    KeyState { bits }
+                }
+
+                pub fn bits(&self) -> u32 
+                {
+                    self.bits
+                }
+
+                pub fn is_down(&self, key: Key) -> bool 
+                {
+                    key != Key::None && self.bits & (1 << key as u32) != 0
+                }
+
+                // Whether any of the keys in keys is down
+                pub fn any_down(&self, keys: KeyState) -> bool 
+                {
+                    self.bits & keys.bits != 0
+                }
+
+                pub fn with(&self, key: Key) -> KeyState 
+                {
+                    if key == Key::None {
+                        return *self;
+                    }
+
+                    KeyState { bits: self.bits | (1 << key as u32) }
+                }
+
+                pub fn without(&self, key: Key) -> KeyState 
+                {
+                    KeyState { bits: self.bits & !(1 << key as u32) }
+                }
+            }
+
+            impl Key 
+            {
+                pub fn from_index(index: u32) -> Key 
+                {
+                    match ALL_KEYS.get(index as usize) {
+                        Some(key) => *key,
+                        None => Key::None,
+                    }
+                }
+
+                // Names used in the config file, letters by themselves, the rest spelled out
+                pub fn from_name(name: &str) -> Option<Key> 
+                {
+                    match name.to_ascii_lowercase().as_str() 
+                    {
+                        "w" => Some(Key::W),
+                        "a" => Some(Key::A),
+                        "s" => Some(Key::S),
+                        "d" => Some(Key::D),
+                        "q" => Some(Key::Q),
+                        "e" => Some(Key::E),
+                        "x" => Some(Key::X),
+                        "p" => Some(Key::P),
+                        "n" => Some(Key::N),
+                        "r" => Some(Key::R),
+                        "f" => Some(Key::F),
+                        "l" => Some(Key::L),
+                        "m" => Some(Key::M),
+                        "t" => Some(Key::T),
+                        "c" => Some(Key::C),
+                        "left" => Some(Key::ArrowLeft),
+                        "up" => Some(Key::ArrowUp),
+                        "right" => Some(Key::ArrowRight),
+                        "down" => Some(Key::ArrowDown),
+                        "space" => Some(Key::Space),
+                        _ => None,
+                    }
+                }
+
+                #[cfg(windows)]
+                pub fn from_vk(code: vk::KEY) -> Key 
+                {
+                    match code 
+                    {
+                        vk::KEY_NONE => Key::None,
+                        vk::KEY_W => Key::W,
+                        vk::KEY_A => Key::A,
+                        vk::KEY_S => Key::S,
+                        vk::KEY_D => Key::D,
+                        vk::KEY_Q => Key::Q,
+                        vk::KEY_E => Key::E,
+                        vk::KEY_X => Key::X,
+                        vk::KEY_ARROW_LEFT => Key::ArrowLeft,
+                        vk::KEY_ARROW_UP => Key::ArrowUp,
+                        vk::KEY_ARROW_RIGHT => Key::ArrowRight,
+                        vk::KEY_ARROW_DOWN => Key::ArrowDown,
+                        vk::KEY_SPACE => Key::Space,
+                        vk::KEY_P => Key::P,
+                        vk::KEY_N => Key::N,
+                        vk::KEY_R => Key::R,
+                        vk::KEY_F => Key::F,
+                        vk::KEY_L => Key::L,
+                        vk::KEY_M => Key::M,
+                        vk::KEY_T => Key::T,
+                        vk::KEY_C => Key::C,
+                        _ => Key::None,
+                    }
+                }
+
+                // What a raw mode terminal sends, locally or over the network
+                pub fn from_byte(byte: u8) -> Key 
+                {
+                    const CTRL_C: u8 = 0x03;
+
+                    match byte.to_ascii_uppercase() 
+                    {
+                        b'W' => Key::W,
+                        b'A' => Key::A,
+                        b'S' => Key::S,
+                        b'D' => Key::D,
+                        b'Q' => Key::Q,
+                        b'E' => Key::E,
+                        b'X' => Key::X,
+                        b' ' => Key::Space,
+                        b'P' => Key::P,
+                        b'N' => Key::N,
+                        b'R' => Key::R,
+                        b'F' => Key::F,
+                        b'L' => Key::L,
+                        b'M' => Key::M,
+                        b'T' => Key::T,
+                        b'C' => Key::C,
+                        // Raw mode swallows SIGINT, so ctrl+c has to be handled like a key
+                        CTRL_C => Key::Interrupt,
+                        _ => Key::None,
+                    }
+                }
+            }
+
+            // Windows virtual key codes
+            #[cfg(windows)]
+            pub mod vk 
+            {
+                pub type KEY = u32;
+
+                pub const KEY_X: KEY = 88;
+                pub const KEY_E: KEY = 69;
+                pub const KEY_Q: KEY = 81;
+                pub const KEY_W: KEY = 87;
+                pub const KEY_S: KEY = 83;
+                pub const KEY_A: KEY = 65;
+                pub const KEY_D: KEY = 68;
+                // Not a key, 0 means no key is down. Windows has no virtual key with that code
+                pub const KEY_NONE: KEY = 0;
+                pub const KEY_ARROW_LEFT: KEY = 37;
+                pub const KEY_ARROW_UP: KEY = 38;
+                pub const KEY_ARROW_RIGHT: KEY = 39;
+                pub const KEY_ARROW_DOWN: KEY = 40;
+                pub const KEY_SPACE: KEY = 32;
+                pub const KEY_P: KEY = 80;
+                pub const KEY_N: KEY = 78;
+                pub const KEY_R: KEY = 82;
+                pub const KEY_F: KEY = 70;
+                pub const KEY_L: KEY = 76;
+                pub const KEY_M: KEY = 77;
+                pub const KEY_T: KEY = 84;
+                pub const KEY_C: KEY = 67;
+            }
+        }
+
+        #[cfg(unix)]
+        static mut OG_ATTR: nix::libc::termios = termios { c_iflag: (0), 
+                                                           c_line: (0),
+                                                           c_oflag: (0),
+                                                           c_lflag: (0),
+                                                           c_ispeed: (0),
+                                                           c_cflag: (0),
+                                                           c_ospeed: (0),
+                                                           c_cc: ([0_u8; 32]) };
+            
+        #[cfg(unix)]
+        extern "C" fn reset_term() 
+        {
+            use nix::libc::{ tcsetattr, TCSANOW };
+
+            unsafe {
+                tcsetattr(0, TCSANOW, std::ptr::addr_of!(OG_ATTR) as *mut termios);   
+            }
+        }
+
+        pub struct Hook 
+        {
+            // The most recently pressed key that's still down
+            key: Arc<atomic::AtomicU32>,
+            // Bits of a KeyState, every key that's down
+            held: Arc<atomic::AtomicU32>,
+            // Every change of held, for presses shorter than a frame
+            events: Arc<EventQueue>,
+            thread_switch: Arc<atomic::AtomicBool>,
+            // See KeyTracker::with_release_delay(), terminals only
+            #[cfg_attr(windows, allow(dead_code))]
+            release_delay: Duration,
+        }
+
+        impl Hook 
+        {
+            // No Default, making one takes over the terminal
+            #[allow(clippy::new_without_default)]
+            pub fn new() -> Hook 
+            {
+                Hook::with_release_delay(DEFAULT_KEY_RELEASE_DELAY)
+            }
+
+            pub fn with_release_delay(release_delay: Duration) -> Hook 
+            {
+                let mut r = Hook {
+                    key: (Arc::new(atomic::AtomicU32::new(keys::Key::None as u32))),
+                    held: Arc::new(atomic::AtomicU32::new(0)),
+                    events: Arc::new(EventQueue::new(EVENT_QUEUE_CAPACITY)),
+                    thread_switch: Arc::new(atomic::AtomicBool::new(true)),
+                    release_delay,
+                };
+
+                r.create_input_thread();
+                r
+            }
+
+            pub fn end(&mut self) 
+            {
+                self.thread_switch.store(false, Ordering::Relaxed);
+            }
+
+            pub fn get_key(&self) -> keys::Key 
+            {
+                keys::Key::from_index(self.key.load(Ordering::Relaxed))
+            }
+
+            // Any key that's down, not only the most recent one
+            pub fn is_key_down(&self, key: keys::Key) -> bool 
+            {
+                self.snapshot().is_down(key)
+            }
+
+            // Every key that's down at this instant. The input thread stores the whole set as one word, 
+            // so keys pressed together are seen together, take one snapshot per frame and query that
+            pub fn snapshot(&self) -> keys::KeyState 
+            {
+                keys::KeyState::from_bits(self.held.load(Ordering::Acquire))
+            }
+
+            #[cfg(unix)]
+            fn create_input_thread(&mut self) 
+            {
+                use nix::libc::{atexit, c_void, cfmakeraw, poll, pollfd, read, tcgetattr, tcsetattr, termios, 
+                                POLLIN, STDIN_FILENO, TCSANOW};
+                use std::ptr::addr_of;
+
+                let og_term = termios { c_iflag: (0), 
+                                        c_line: (0),
+                                        c_oflag: (0),
+                                        c_lflag: (0),
+                                        c_ispeed: (0), 
+                                        c_cflag: (0),
+                                        c_ospeed: (0),
+                                        c_cc: ([0_u8; 32]) };
+
+                unsafe {
+                    tcgetattr(0, addr_of!(og_term) as *mut termios);
+
+                    OG_ATTR = og_term;
+                    let new_term = og_term;
+                
+                    atexit(reset_term);
+                    cfmakeraw(addr_of!(new_term) as *mut termios);
+                    tcsetattr(0, TCSANOW, std::ptr::addr_of!(new_term) as *mut termios);   
+                }
+    
+                let switch_clone = self.thread_switch.clone();
+                let key_clone = self.key.clone();
+                let held_clone = self.held.clone();
+                let events_clone = self.events.clone();
+                let release_delay = self.release_delay;
+
+                spawn(move || {
+                    let mut buf = [0_u8; 16];
+                    let mut tracker = KeyTracker::with_release_delay(release_delay);
+                    let mut last_held = keys::KeyState::default();
+
+                    while switch_clone.load(Ordering::Relaxed) 
+                    {
+                        let mut fds = pollfd { fd: STDIN_FILENO, events: POLLIN, revents: 0 };
+
+                        if unsafe { poll(&mut fds, 1, KEY_POLL_MS) } > 0 
+                        {
+                            let n = unsafe { read(STDIN_FILENO, buf.as_mut_ptr() as *mut c_void, buf.len()) };
+                            // A down for every key read, two taps between frames are two presses
+                            if n > 0 {
+                                tracker.press_bytes_with(&buf[..n as usize], |key| {
+                                    events_clone.push_key(key, true);
+                                });
+                            }
+                        }
+
+                        let (last_key, held) = tracker.poll();
+
+                        // The terminal never says when a key goes up, that's when the tracker lets go of it
+                        let released = last_held.bits() & !held.bits();
+                        for index in (0..32).filter(|index| released & (1 << index) != 0) 
+                        {
+                            events_clone.push_key(keys::Key::from_index(index), false);
+                        }
+                        last_held = held;
+
+                        key_clone.store(last_key as u32, Ordering::Relaxed);
+                        held_clone.store(held.bits(), Ordering::Release);
+                    }
+                });
+            }
+
+            #[cfg(windows)]
+            fn create_input_thread(&mut self) 
+            {
+                use winapi::shared::windef::HWND;
+                use winapi::shared::windef::POINT;
+                use winapi::um::winuser::MSG;
+                use winapi::um::winuser::PeekMessageA;
+                use winapi::um::winuser::PM_REMOVE;
+                use winapi::um::winuser::PM_QS_INPUT;
+
+                let switch_clone = self.thread_switch.clone();
+                let key_clone = self.key.clone();
+                let held_clone = self.held.clone();
+                let events_clone = self.events.clone();
+
+                spawn(move || {                    
+                    let mut last_held = keys::KeyState::default();
+                    let mut msg = MSG {
+                        hwnd: 0 as HWND,
+                        message: 0 as u32,
+                        wParam: 0 as usize,
+                        lParam: 0 as isize,
+                        time: 0,
+                        pt: POINT { x: 0, y: 0 },
+                    }; 
+
+                    let hook_id = set_up_kb_hook();
+
+                    loop 
+                    {
+                        unsafe {
+                            if PeekMessageA(&mut msg,
+                                            -1_i32 as HWND,
+                                            0,
+                                            0,
+                                            PM_REMOVE  | PM_QS_INPUT) == 0 
+                            {
+                                let held = keys::KeyState::from_bits(_HELD);
+
+                                events_clone.push_changes(last_held, held);
+                                last_held = held;
+
+                                key_clone.store(keys::Key::from_vk(_KEY) as u32, Ordering::Relaxed);
+                                held_clone.store(held.bits(), Ordering::Release);
+                            }
+                        }
+
+                        if !switch_clone.load(Ordering::Relaxed) {
+                            break;
+                        }
+                    }
+
+                    end_kb_hook(hook_id);
+                });
+            }
+        }
+
+        impl Drop for Hook 
+        {
+            fn drop(&mut self) 
+            {
+                self.end();
+                clean_up();
+
+                #[cfg(unix)]
+                reset_term();
+            }
+        }
+
+        // Anything the keys can come from, the keyboard, a socket or a script
+        pub trait InputSource 
+        {
+            // Every key that's down right now, called once a frame
+            fn poll(&mut self) -> keys::KeyState;
+
+            // Keys that went down or up since the last call, oldest first, appended to events. 
+            // Sources that only know what's down when they're polled have none
+            fn drain_events(&mut self, 
+                            _events: &mut Vec<KeyEvent>) 
+            {
+            }
+        }
+
+        impl InputSource for Hook 
+        {
+            fn poll(&mut self) -> keys::KeyState 
+            {
+                self.snapshot()
+            }
+
+            fn drain_events(&mut self, 
+                            events: &mut Vec<KeyEvent>) 
+            {
+                self.events.drain(events);
+            }
+        }
+
+        // Gives out a fixed list of key states, one per poll(), nothing is down once it runs out
+        pub struct ScriptedInput 
+        {
+            frames: Vec<keys::KeyState>,
+            next: usize,
+        }
+
+        impl ScriptedInput 
+        {
+            pub fn new(frames: Vec<keys::KeyState>) -> ScriptedInput 
+            {
+                ScriptedInput { frames, next: 0 }
+            }
+
+            pub fn is_done(&self) -> bool 
+            {
+                self.next >= self.frames.len()
+            }
+        }
+
+        impl InputSource for ScriptedInput 
+        {
+            fn poll(&mut self) -> keys::KeyState 
+            {
+                let keys = self.frames.get(self.next).copied().unwrap_or_default();
+                self.next += 1;
+
+                keys
+            }
+        }
+
+        // How long a terminal waits before it starts repeating a held key, a key that was pressed once 
+        // is down for this long. Most terminals wait 250 to 660 milliseconds
+        pub const DEFAULT_KEY_RELEASE_DELAY: Duration = Duration::from_millis(600);
+        pub const MAX_KEY_RELEASE_DELAY: Duration = Duration::from_secs(2);
+        // Once the repeats are coming a few arriving late is enough to tell the key was let go
+        const REPEAT_RELEASE_TIMEOUT: Duration = Duration::from_millis(100);
+        #[cfg(unix)]
+        const KEY_POLL_MS: i32 = 10;
+
+        // Held keys from the bytes a raw mode terminal sends, the local one or one on the other 
+        // end of a socket. Terminals don't report key releases, a key is considered up 
+        // once its autorepeat stops arriving
+        pub struct KeyTracker 
+        {
+            last_seen: [Option<Instant>; 32],
+            last_key: keys::Key,
+            // Bits of the keys that came again while they were down, they're being repeated
+            repeating: u32,
+            release_delay: Duration,
+        }
+
+        impl Default for KeyTracker 
+        {
+            fn default() -> KeyTracker 
+            {
+                KeyTracker::new()
+            }
+        }
+
+        impl KeyTracker 
+        {
+            pub fn new() -> KeyTracker 
+            {
+                KeyTracker::with_release_delay(DEFAULT_KEY_RELEASE_DELAY)
+            }
+
+            // The release delay should be at least the terminal's autorepeat delay, a shorter one lets 
+            // go of a held key until the repeats start
+            pub fn with_release_delay(release_delay: Duration) -> KeyTracker 
+            {
+                KeyTracker {
+                    last_seen: [None; 32],
+                    last_key: keys::Key::None,
+                    repeating: 0,
+                    release_delay: release_delay.min(MAX_KEY_RELEASE_DELAY),
+                }
+            }
+
+            // One read can hold several keys, every one of them counts
+            pub fn press_bytes(&mut self, 
+                               bytes: &[u8]) 
+            {
+                self.press_bytes_with(bytes, |_| {});
+            }
+
+            // Same as press_bytes(), with on_press called for every key in the order they were read
+            pub fn press_bytes_with(&mut self, 
+                                    mut bytes: &[u8], 
+                                    mut on_press: impl FnMut(keys::Key)) 
+            {
+                let now = Instant::now();
+
+                while !bytes.is_empty() 
+                {
+                    let (key, length) = parse_key_bytes(bytes);
+                    bytes = &bytes[length..];
+
+                    if key != keys::Key::None {
+                        if self.is_held(key as usize, now) {
+                            self.repeating |= 1 << key as u32;
+                        }
+
+                        self.last_seen[key as usize] = Some(now);
+                        self.last_key = key;
+                        on_press(key);
+                    }
+                }
+            }
+
+            // The most recently pressed key that's still down and every key that's down
+            pub fn poll(&mut self) -> (keys::Key, keys::KeyState) 
+            {
+                self.poll_at(Instant::now())
+            }
+
+            fn poll_at(&mut self, 
+                       now: Instant) -> (keys::Key, keys::KeyState) 
+            {
+                let mut held = keys::KeyState::default();
+                for index in 0..self.last_seen.len() 
+                {
+                    if self.is_held(index, now) {
+                        held = held.with(keys::Key::from_index(index as u32));
+                    }
+                    else {
+                        self.repeating &= !(1 << index);
+                    }
+                }
+
+                if !held.is_down(self.last_key) {
+                    self.last_key = keys::Key::None;
+                }
+
+                (self.last_key, held)
+            }
+
+            fn is_held(&self, 
+                       index: usize, 
+                       now: Instant) -> bool 
+            {
+                let timeout = match self.repeating & (1 << index) != 0 {
+                    true => REPEAT_RELEASE_TIMEOUT,
+                    false => self.release_delay,
+                };
+
+                self.last_seen[index].is_some_and(|seen| now.saturating_duration_since(seen) < timeout)
+            }
+        }
+
+        // A key going down or up, at is how long after the queue was made
+        #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+        pub struct KeyEvent 
+        {
+            pub key: keys::Key,
+            pub down: bool,
+            pub at: Duration,
+        }
+
+        // Events the game hasn't drained yet, a few seconds of mashing keys
+        pub const EVENT_QUEUE_CAPACITY: usize = 256;
+
+        // Bounded ring of key events between one thread pushing and one draining, the input thread 
+        // and the game. Events are packed into one word each, so neither side ever waits on a lock. 
+        // A full queue drops the new events, the held keys are still right in the snapshot
+        pub struct EventQueue 
+        {
+            slots: Box<[AtomicU64]>,
+            // Next slot to drain and next to push, counting up forever, slot is the count modulo length
+            head: AtomicUsize,
+            tail: AtomicUsize,
+            start: Instant,
+        }
+
+        impl EventQueue 
+        {
+            // Bits of a packed event, the key's index, then whether it went down, then the microseconds
+            const KEY_BITS: u32 = 5;
+            const DOWN_BIT: u64 = 1 << Self::KEY_BITS;
+            const AT_SHIFT: u32 = Self::KEY_BITS + 1;
+
+            pub fn new(capacity: usize) -> EventQueue 
+            {
+                EventQueue {
+                    slots: (0..capacity.max(1)).map(|_| AtomicU64::new(0)).collect(),
+                    head: AtomicUsize::new(0),
+                    tail: AtomicUsize::new(0),
+                    start: Instant::now(),
+                }
+            }
+
+            // From the pushing thread only. False when the queue was full and the event got dropped
+            pub fn push(&self, 
+                        event: KeyEvent) -> bool 
+            {
+                let tail = self.tail.load(Ordering::Relaxed);
+
+                if tail.wrapping_sub(self.head.load(Ordering::Acquire)) >= self.slots.len() {
+                    return false;
+                }
+
+                let packed = (event.key as u64) 
+                    | if event.down { Self::DOWN_BIT } else { 0 } 
+                    | (event.at.as_micros() as u64) << Self::AT_SHIFT;
+
+                self.slots[tail % self.slots.len()].store(packed, Ordering::Relaxed);
+                self.tail.store(tail.wrapping_add(1), Ordering::Release);
+                true
+            }
+
+            // One event stamped now, false when the queue is full
+            pub fn push_key(&self, 
+                            key: keys::Key, 
+                            down: bool) -> bool 
+            {
+                self.push(KeyEvent { key, down, at: self.start.elapsed() })
+            }
+
+            // An event for every key that's down in one and not the other, stamped now
+            pub fn push_changes(&self, 
+                                before: keys::KeyState, 
+                                after: keys::KeyState) 
+            {
+                let changed = before.bits() ^ after.bits();
+                if changed == 0 {
+                    return;
+                }
+
+                let at = self.start.elapsed();
+                for index in (0..32).filter(|index| changed & (1 << index) != 0) 
+                {
+                    let key = keys::Key::from_index(index);
+                    self.push(KeyEvent { key, down: after.is_down(key), at });
+                }
+            }
+
+            // From the draining thread only, appends everything pushed so far in the order it was pushed
+            pub fn drain(&self, 
+                         events: &mut Vec<KeyEvent>) 
+            {
+                let mut head = self.head.load(Ordering::Relaxed);
+                let tail = self.tail.load(Ordering::Acquire);
+
+                while head != tail 
+                {
+                    let packed = self.slots[head % self.slots.len()].load(Ordering::Relaxed);
+
+                    events.push(KeyEvent {
+                        key: keys::Key::from_index((packed & (Self::DOWN_BIT - 1)) as u32),
+                        down: packed & Self::DOWN_BIT != 0,
+                        at: Duration::from_micros(packed >> Self::AT_SHIFT),
+                    });
+                    head = head.wrapping_add(1);
+                }
+
+                self.head.store(head, Ordering::Release);
+            }
+        }
+
+        // Longest a released key can still count as held for
+        pub const MAX_DEBOUNCE: Duration = Duration::from_secs(2);
+
+        // Keys the way edge triggered actions see them, a key stays down until it's been up for the
+        // whole window. A terminal's autorepeat starts with a pause longer than the release timeout, 
+        // so without it one long press reads as two. Held actions like walking use the keys as they are
+        #[derive(Copy, Clone, Debug)]
+        pub struct Debounce 
+        {
+            window: f32,
+            // Seconds since each key was last down
+            up_for: [f32; 32],
+            // When each key last had an event, seconds by their clock
+            last_event_at: [f32; 32],
+        }
+
+        impl Debounce 
+        {
+            // A zero window passes the keys through
+            pub fn new(window: Duration) -> Debounce 
+            {
+                Debounce {
+                    window: window.min(MAX_DEBOUNCE).as_secs_f32(),
+                    up_for: [f32::INFINITY; 32],
+                    last_event_at: [f32::NEG_INFINITY; 32],
+                }
+            }
+
+            // The same for events, a key going down again within the window of its last event 
+            // is still the same press, so that down is left out. On a terminal that's also what 
+            // keeps the autorepeat of a held key from counting as more presses
+            pub fn filter_events(&mut self, 
+                                 events: &mut Vec<KeyEvent>) 
+            {
+                let window = self.window;
+                let last_event_at = &mut self.last_event_at;
+
+                events.retain(|event| {
+                    let index = event.key as usize;
+                    let at = event.at.as_secs_f32();
+                    let since = at - last_event_at[index];
+                    last_event_at[index] = at;
+
+                    !event.down || since >= window
+                });
+            }
+
+            // Counts with the frame's dt rather than the clock, so replays see the same presses
+            pub fn filter(&mut self, 
+                          keys: keys::KeyState, 
+                          dt: f32) -> keys::KeyState 
+            {
+                let mut debounced = keys::KeyState::default();
+                for (index, up_for) in self.up_for.iter_mut().enumerate() 
+                {
+                    let key = keys::Key::from_index(index as u32);
+
+                    if keys.is_down(key) {
+                        *up_for = 0.;
+                    } 
+                    else {
+                        *up_for += dt;
+                    }
+
+                    if *up_for < self.window || keys.is_down(key) {
+                        debounced = debounced.with(key);
+                    }
+                }
+
+                debounced
+            }
+        }
+
+        // The key at the start of bytes and how many bytes it took
+        fn parse_key_bytes(bytes: &[u8]) -> (keys::Key, usize) 
+        {
+            const ESC: u8 = 0x1B;
+
+            match bytes 
+            {
+                [ESC, b'[', b'A', ..] => (keys::Key::ArrowUp, 3),
+                [ESC, b'[', b'B', ..] => (keys::Key::ArrowDown, 3),
+                [ESC, b'[', b'C', ..] => (keys::Key::ArrowRight, 3),
+                [ESC, b'[', b'D', ..] => (keys::Key::ArrowLeft, 3),
+                [b, ..] => (keys::Key::from_byte(*b), 1),
+                [] => (keys::Key::None, 0),
+            }
+        }
+
+        pub fn clean_up() 
+        {
+            // let mut f = String::new();
+            // let _x = std::io::stdin().read_line(&mut f);
+        }
+
+        #[cfg(windows)]
+        const WH_KEYBOARD_LL: i32 = 13;
+
+        #[cfg(windows)]
+        fn set_up_kb_hook() -> winapi::shared::windef::HHOOK 
+        {
+            use winapi::um::winuser::SetWindowsHookExA;
+
+            #[expect(unused_assignments)]
+            let mut r: winapi::shared::windef::HHOOK = null_mut();
+
+            unsafe {
+                r = SetWindowsHookExA(
+                    WH_KEYBOARD_LL, 
+                    Some(windows_ll_hook), 
+                    null_mut(), 
+                    0);
+
+                if r as i32 == 0 {
+                    panic!("Couldn't create a hook in winapi, \
+                        GetLastError() returned {err_code}", 
+                        err_code = crate::windows_errors::get_last_error());
+                }
+            }
+
+            return r;
+        }
+
+        #[cfg(windows)]
+        static mut _KEY: keys::vk::KEY = keys::vk::KEY_NONE;
+
+        // Bits of a KeyState, the hook sees every key going down and up
+        #[cfg(windows)]
+        static mut _HELD: u32 = 0;
+
+        #[cfg(windows)]
+        unsafe extern "system" fn windows_ll_hook(code: i32, 
+                                                  w_param: usize, 
+                                                  l_param: isize) -> isize 
+        {
+            use winapi::um::winuser::CallNextHookEx;
+            use winapi::um::winuser::KBDLLHOOKSTRUCT;
+            use winapi::um::winuser::WM_KEYDOWN;
+            use winapi::um::winuser::WM_KEYUP;
+
+            let kbd: &KBDLLHOOKSTRUCT = (l_param as *const KBDLLHOOKSTRUCT).as_ref().unwrap();
+
+            if w_param == WM_KEYDOWN as usize {
+                _KEY = kbd.vkCode;
+                _HELD = keys::KeyState::from_bits(_HELD).with(keys::Key::from_vk(kbd.vkCode)).bits();
+            }
+            // Letting go of some other key doesn't mean the last pressed one is up
+            if w_param == WM_KEYUP as usize {
+                if _KEY == kbd.vkCode {
+                    _KEY = keys::vk::KEY_NONE;
+                }
+
+                _HELD = keys::KeyState::from_bits(_HELD).without(keys::Key::from_vk(kbd.vkCode)).bits();
+            }
+
+            CallNextHookEx(null_mut(), code, w_param, l_param)
+        }
+
+        #[cfg(windows)]
+        fn end_kb_hook(hk: winapi::shared::windef::HHOOK) 
+        {
+            use winapi::um::winuser::UnhookWindowsHookEx;
+
+            unsafe {
+                if UnhookWindowsHookEx(hk) == 0 {
+                    panic!("Couldn't unhook keyboard hook in winapi, GetLastError() returned {err_code}", 
+                           err_code = crate::windows_errors::get_last_error());
+                }
+            }
+        }
+
+        #[cfg(test)]
+        mod tests 
+        {
+            use super::*;
+
+            fn event(key: keys::Key, 
+                     down: bool, 
+                     millis: u64) -> KeyEvent 
+            {
+                KeyEvent { key, down, at: Duration::from_millis(millis) }
+            }
+
+            // A chord goes down and up at once while frames take snapshots, none of them catches 
+            // only part of it. A thread stands in for the hook's input thread, and the hook isn't dropped 
+            // since that would reset the terminal
+            #[test]
+            fn snapshots_see_keys_pressed_together_together() 
+            {
+                let hook = std::mem::ManuallyDrop::new(Hook {
+                    key: Arc::new(atomic::AtomicU32::new(keys::Key::None as u32)),
+                    held: Arc::new(atomic::AtomicU32::new(0)),
+                    events: Arc::new(EventQueue::new(EVENT_QUEUE_CAPACITY)),
+                    thread_switch: Arc::new(atomic::AtomicBool::new(false)),
+                    release_delay: DEFAULT_KEY_RELEASE_DELAY,
+                });
+                let chord = keys::KeyState::default().with(keys::Key::W).with(keys::Key::A).with(keys::Key::Space);
+
+                let held = hook.held.clone();
+                let running = hook.thread_switch.clone();
+                running.store(true, Ordering::Relaxed);
+                let input_thread = std::thread::spawn(move || {
+                    while running.load(Ordering::Relaxed) 
+                    {
+                        held.store(chord.bits(), Ordering::Release);
+                        held.store(0, Ordering::Release);
+                    }
+                });
+
+                // Until both have been seen plenty of times
+                let (mut down, mut up) = (0, 0);
+                while down < 1000 || up < 1000 
+                {
+                    let snapshot = hook.snapshot();
+                    assert!(snapshot.bits() == 0 || snapshot.bits() == chord.bits(), "{:#b}", snapshot.bits());
+
+                    match snapshot.is_down(keys::Key::W) {
+                        true => down += 1,
+                        false => up += 1,
+                    }
+                }
+
+                hook.thread_switch.store(false, Ordering::Relaxed);
+                input_thread.join().unwrap();
+                assert!(!hook.is_key_down(keys::Key::W));
+            }
+
+            #[test]
+            fn every_byte_read_is_a_press() 
+            {
+                let mut tracker = KeyTracker::new();
+                let mut pressed = Vec::new();
+
+                tracker.press_bytes_with(b"pp\x1b[Dp", |key| pressed.push(key));
+
+                assert_eq!(pressed, [keys::Key::P, keys::Key::P, keys::Key::ArrowLeft, keys::Key::P]);
+            }
+
+            // One press has to last through the terminal's pause before it repeats the key
+            #[test]
+            fn pressed_key_is_held_for_the_release_delay() 
+            {
+                let mut tracker = KeyTracker::with_release_delay(Duration::from_millis(500));
+                let start = Instant::now();
+
+                tracker.press_bytes(b"w");
+
+                assert_eq!(tracker.poll_at(start + Duration::from_millis(400)), 
+                           (keys::Key::W, keys::KeyState::default().with(keys::Key::W)));
+                assert_eq!(tracker.poll_at(start + Duration::from_millis(600)), 
+                           (keys::Key::None, keys::KeyState::default()));
+            }
+
+            #[test]
+            fn repeating_key_is_let_go_soon_after_the_repeats_stop() 
+            {
+                let mut tracker = KeyTracker::with_release_delay(Duration::from_millis(500));
+                let start = Instant::now();
+
+                tracker.press_bytes(b"ww");
+                assert!(tracker.poll_at(start + REPEAT_RELEASE_TIMEOUT / 2).1.is_down(keys::Key::W));
+                assert!(!tracker.poll_at(start + REPEAT_RELEASE_TIMEOUT * 2).1.is_down(keys::Key::W));
+            }
+
+            #[test]
+            fn event_queue_drains_in_order() 
+            {
+                let queue = EventQueue::new(4);
+                let mut events = Vec::new();
+
+                assert!(queue.push_key(keys::Key::P, true));
+                assert!(queue.push_key(keys::Key::P, false));
+                assert!(queue.push_key(keys::Key::M, true));
+                queue.drain(&mut events);
+
+                let order: Vec<_> = events.iter().map(|event| (event.key, event.down)).collect();
+                assert_eq!(order, [(keys::Key::P, true), (keys::Key::P, false), (keys::Key::M, true)]);
+            }
+
+            // A terminal repeating a held key sends more downs before the tracker lets go of it
+            #[test]
+            fn debounce_drops_downs_within_the_window() 
+            {
+                let mut debounce = Debounce::new(Duration::from_millis(600));
+                let mut events = vec![
+                    event(keys::Key::P, true, 1000), 
+                    event(keys::Key::P, true, 1500), 
+                    event(keys::Key::P, true, 1530), 
+                    event(keys::Key::M, true, 1540), 
+                    event(keys::Key::P, false, 1630), 
+                    event(keys::Key::P, true, 2300),
+                ];
+
+                debounce.filter_events(&mut events);
+
+                let kept: Vec<_> = events.iter().map(|event| (event.key, event.down, event.at.as_millis())).collect();
+                assert_eq!(kept, [(keys::Key::P, true, 1000), 
+                                  (keys::Key::M, true, 1540), 
+                                  (keys::Key::P, false, 1630), 
+                                  (keys::Key::P, true, 2300)]);
+            }
+
+            #[test]
+            fn zero_debounce_keeps_every_event() 
+            {
+                let mut debounce = Debounce::new(Duration::ZERO);
+                let mut events = vec![event(keys::Key::P, true, 10), event(keys::Key::P, true, 10)];
+
+                debounce.filter_events(&mut events);
+
+                assert_eq!(events.len(), 2);
+            }
+        }
+    }
+}
+
+pub mod game_logic 
+{
+    use std::collections::{HashMap, VecDeque};
+    use std::f32::consts::PI;
+    use std::time::{Duration, Instant};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::fmt::Write;
+    use crate::points_distance;
+    use crate::config::Config;
+    use crate::terminal::output::{
+        BLACK_BOX_CHAR, 
+        SPARK_CHAR,
+        SHADING_RAMP,
+        STRIP_BOX_CHAR,
+        WALL_2D_CHAR,
+        EXIT_2D_CHAR,
+        PLAYER_2D_CHAR,
+        FACING_CHAR,
+        RING_CHAR,
+        CONE_CHAR,
+        HIT_CHAR,
+        ACTOR_CHAR,
+        DIVIDER_CHAR,
+        DEFAULT_DOT_RADIUS,
+        RenderPreset,
+        CROSSHAIR_CHAR,
+        TRANSPARENT_CHAR,
+        Color,
+        DEFAULT_COLOR};
+    use crate::{
+        terminal::{
+            input::{keys::{Key, KeyState}, Debounce, KeyEvent}, output::Renderer},
+        Vec2};
+
+    // World time between two ticks, the steps everything that moves in discrete steps takes
+    pub const TICK_DURATION: Duration = Duration::from_millis(600);
+    pub const MIN_TICK_DURATION: Duration = Duration::from_millis(10);
+    pub const MAX_TICK_DURATION: Duration = Duration::from_secs(10);
+    // A frame slower than this many ticks drops the rest, instead of catching up on all of them
+    const MAX_TICKS_PER_FRAME: u32 = 8;
+
+    // World units per second, a cell of the built in map
+    pub const PLAYER_MOVEMENT_SPEED: f32 = 25.;
+    // Radians per second
+    pub const PLAYER_ROTATION_SPEED: f32 = 0.5;
+
+    pub const DEFAULT_FOV: f32 = 90.;
+    // Degrees, 0 leaves the vertical fov to fov and cell_aspect, see Game::projection_plane_distance()
+    pub const DEFAULT_VERTICAL_FOV: f32 = 0.;
+    pub const MAX_VERTICAL_FOV: f32 = 170.;
+    pub const DEFAULT_VISIBLE_DISTANCE: i32 = 15;
+
+    const MAX_PARTICLES: usize = 64;
+    const PARTICLE_BURST: usize = 8;
+    const PARTICLE_LIFE: f32 = 0.4;
+    const PARTICLE_SPEED: f32 = 20.;
+    const PARTICLE_SPREAD: f32 = HALF_PI;
+
+    pub const DEFAULT_COMPASS_POSITION: Vec2<i32> = Vec2 { x: 0, y: 2 };
+
+    // Shown under the crosshair while the player faces an exit, see Game::interact_target()
+    pub const DEFAULT_INTERACT_PROMPT: &str = "Exit, walk in for the next level";
+    // Cells
+    pub const DEFAULT_INTERACT_RANGE: f32 = 1.5;
+    pub const MAX_INTERACT_RANGE: f32 = 10.;
+
+    pub const DEFAULT_SEED: u64 = 0x2545F4914F6CDD1D;
+
+    const FPS_SAMPLES: usize = 32;
+
+    const DEFAULT_WEAPON_ART: &str = "   __   \n  |==|  \n _|  |_ \n/      \\\n\n  \\||/  \n  |==|  \n _|  |_ \n/      \\\n";
+    const WEAPON_FLASH_TIME: f32 = 0.12;
+    // Cells the weapon sways to the sides and bobs up, and cells walked per sway
+    const WEAPON_SWAY: f32 = 2.;
+    const WEAPON_BOB: f32 = 1.;
+    const WEAPON_BOB_LENGTH: f32 = 1.;
+
+    // Height the horizon bobs up and down while walking, as a fraction of the screen height, 
+    // and bobs per cell walked
+    pub const DEFAULT_VIEW_BOB_AMPLITUDE: f32 = 0.02;
+    pub const MAX_VIEW_BOB_AMPLITUDE: f32 = 0.2;
+    pub const DEFAULT_VIEW_BOB_FREQUENCY: f32 = 1.;
+    pub const MAX_VIEW_BOB_FREQUENCY: f32 = 4.;
+    // Seconds the bob takes to fade in after starting to walk, and out after stopping
+    const VIEW_BOB_FADE: f32 = 0.25;
+    // Cells the view jitters by in the strongest shake, and cells a second it settles by
+    pub const MAX_SHAKE: f32 = 2.;
+    const SHAKE_SETTLE: f32 = 4.;
+    // A shot hitting a wall
+    const FIRE_SHAKE: f32 = 0.5;
+
+    // The message log keeps this many, the oldest go first, and shows the newest few of them
+    const MESSAGE_CAPACITY: usize = 16;
+    const MESSAGES_SHOWN: usize = 3;
+    // Seconds a message stays, it dims during the last one
+    const MESSAGE_LIFE: f32 = 4.;
+
+    // Height of a terminal cell divided by its width, most fonts are about twice as tall as wide
+    pub const DEFAULT_CELL_ASPECT: f32 = 2.;
+    // 0.0001 world units on the built in map's 25 unit cells
+    pub const DEFAULT_BOUNDARY_EPSILON: f32 = 0.000004;
+    pub const MAX_BOUNDARY_EPSILON: f32 = 0.1;
+
+    pub const DEFAULT_SIDE_SHADE: f32 = 0.75;
+    pub const DEFAULT_GAMMA: f32 = 1.;
+    pub const MIN_GAMMA: f32 = 0.1;
+    pub const MAX_GAMMA: f32 = 5.;
+
+    // Time in the world passes this many times faster than real time, 0 freezes it
+    pub const DEFAULT_TIME_SCALE: f32 = 1.;
+    pub const MAX_TIME_SCALE: f32 = 4.;
+
+    // L cycles the column step from 1 up to this
+    pub const MAX_COLUMN_STEP: u8 = 4;
+    pub const MAX_RAY_COUNT: u32 = 8192;
+
+    // tan() runs off to infinity at 90, wider fields of view are projected like this one
+    const MAX_PROJECTED_HALF_FOV: f32 = 89. * RADIAN;
+
+    // Below this many terminal cells the scene isn't drawn at all, only a note about it
+    const MIN_SCREEN_CELLS: Vec2<i16> = Vec2 { x: 20, y: 6 };
+    const TOO_SMALL_MESSAGE: &str = "Terminal too small";
+
+    // How far the horizon can be sheared, as a fraction of the screen height
+    const MAX_VERTICAL_LOOK: f32 = 0.4;
+    const VERTICAL_LOOK_SPEED: f32 = 1.;
+
+    const TWO_PI: f32 = std::f32::consts::TAU;
+    const HALF_PI: f32 = 1.570795;
+    const DEGREE: f32 = 57.29578;
+    const RADIAN: f32 = 0.01745329;
+
+    // Width and height of the built in map's cells, in world units
+    const BUILT_IN_CELL_SIZE: f32 = 25.;
+    // Where maps that don't say otherwise put the player, the middle of a cell that's open in the 
+    // built in map. Game::new() checks that it is, so editing the map can't start the player in a wall
+    const DEFAULT_SPAWN_CELL: Vec2<i32> = Vec2 { x: 2, y: 2 };
+    const DEFAULT_SPAWN: Vec2<f32> = Vec2 { 
+        x: (DEFAULT_SPAWN_CELL.x as f32 + 0.5) * BUILT_IN_CELL_SIZE, 
+        y: (DEFAULT_SPAWN_CELL.y as f32 + 0.5) * BUILT_IN_CELL_SIZE,
+    };
+    // South, down the longest open column of the built in map
+    const DEFAULT_SPAWN_YAW: f32 = PI;
+
+    // Seconds before the autopilot gives up on a waypoint it can't reach
+    const WAYPOINT_TIMEOUT: f32 = 10.;
+    // The autopilot only walks when the waypoint is at most this far off to the side
+    const AUTOPILOT_WALK_ANGLE: f32 = 30. * RADIAN;
+
+    // Walking onto this cell takes the player to the next level
+    const EXIT_CELL: i32 = 9;
+
+    // Once per press, the rest of the actions go on for as long as their keys are down
+    const TOGGLE_ACTIONS: [Action; 4] = [Action::Pause, Action::Noclip, Action::ColumnStep, Action::Spectate];
+    // Marks the spawn in text maps
+    const SPAWN_CELL: char = 'S';
+    // A pillar of DEFAULT_PILLAR_RADIUS in text maps
+    const PILLAR_CELL: char = 'O';
+    // Fraction of the cell's shorter side, 0.5 touches its borders
+    const DEFAULT_PILLAR_RADIUS: f32 = 0.3;
+    const MIN_PILLAR_RADIUS: f32 = 0.05;
+    const MAX_PILLAR_RADIUS: f32 = 0.5;
+
+    #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+    pub enum ViewMode 
+    {
+        Mode2d,
+        Mode3d,
+        // Mode2d and Mode3d side by side
+        Mode2dAnd3d,
+        // Mode3d with only the outlines of the walls
+        Wireframe,
+    }
+
+    impl ViewMode 
+    {
+        // Order the view toggle goes through
+        pub fn next(&self) -> ViewMode 
+        {
+            match self 
+            {
+                ViewMode::Mode3d => ViewMode::Mode2dAnd3d,
+                ViewMode::Mode2dAnd3d => ViewMode::Mode2d,
+                ViewMode::Mode2d => ViewMode::Wireframe,
+                ViewMode::Wireframe => ViewMode::Mode3d,
+            }
+        }
+    }
+
+    // What the keys do, KeyBindings says which keys do it
+    #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+    pub enum Action 
+    {
+        Forward,
+        Back,
+        StrafeLeft,
+        StrafeRight,
+        TurnLeft,
+        TurnRight,
+        LookUp,
+        LookDown,
+        Fire,
+        Pause,
+        Noclip,
+        ColumnStep,
+        ToggleView,
+        DumpView,
+        Spectate,
+        Quit,
+    }
+
+    const ACTION_COUNT: usize = 16;
+
+    impl Action 
+    {
+        pub const ALL: [Action; ACTION_COUNT] = [
+            Action::Forward,
+            Action::Back,
+            Action::StrafeLeft,
+            Action::StrafeRight,
+            Action::TurnLeft,
+            Action::TurnRight,
+            Action::LookUp,
+            Action::LookDown,
+            Action::Fire,
+            Action::Pause,
+            Action::Noclip,
+            Action::ColumnStep,
+            Action::ToggleView,
+            Action::DumpView,
+            Action::Spectate,
+            Action::Quit,
+        ];
+
+        // Names used in the config file
+        pub fn name(&self) -> &'static str 
+        {
+            match self 
+            {
+                Action::Forward => "forward",
+                Action::Back => "back",
+                Action::StrafeLeft => "strafe_left",
+                Action::StrafeRight => "strafe_right",
+                Action::TurnLeft => "turn_left",
+                Action::TurnRight => "turn_right",
+                Action::LookUp => "look_up",
+                Action::LookDown => "look_down",
+                Action::Fire => "fire",
+                Action::Pause => "pause",
+                Action::Noclip => "noclip",
+                Action::ColumnStep => "column_step",
+                Action::ToggleView => "toggle_view",
+                Action::DumpView => "dump_view",
+                Action::Spectate => "spectate",
+                Action::Quit => "quit",
+            }
+        }
+
+        pub fn from_name(name: &str) -> Option<Action> 
+        {
+            Action::ALL.iter().copied().find(|action| action.name() == name)
+        }
+    }
+
+    // Keys of every action, any one of them triggers it
+    #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+    pub struct KeyBindings 
+    {
+        keys: [KeyState; ACTION_COUNT],
+    }
+
+    impl Default for KeyBindings 
+    {
+        // WASD walks, QE turns and the arrows are the second scheme, up and down walk, left and right turn
+        fn default() -> KeyBindings 
+        {
+            let mut bindings = KeyBindings { keys: [KeyState::default(); ACTION_COUNT] };
+
+            bindings.bind(Action::Forward, &[Key::W, Key::ArrowUp]);
+            bindings.bind(Action::Back, &[Key::S, Key::ArrowDown]);
+            bindings.bind(Action::StrafeLeft, &[Key::A]);
+            bindings.bind(Action::StrafeRight, &[Key::D]);
+            bindings.bind(Action::TurnLeft, &[Key::Q, Key::ArrowLeft]);
+            bindings.bind(Action::TurnRight, &[Key::E, Key::ArrowRight]);
+            bindings.bind(Action::LookUp, &[Key::R]);
+            bindings.bind(Action::LookDown, &[Key::F]);
+            bindings.bind(Action::Fire, &[Key::Space]);
+            bindings.bind(Action::Pause, &[Key::P]);
+            bindings.bind(Action::Noclip, &[Key::N]);
+            bindings.bind(Action::ColumnStep, &[Key::L]);
+            bindings.bind(Action::ToggleView, &[Key::M]);
+            bindings.bind(Action::DumpView, &[Key::T]);
+            bindings.bind(Action::Spectate, &[Key::C]);
+            bindings.bind(Action::Quit, &[Key::X]);
+
+            bindings
+        }
+    }
+
+    impl KeyBindings 
+    {
+        // Replaces the action's keys, no keys leaves it unbound
+        pub fn bind(&mut self, action: Action, keys: &[Key]) 
+        {
+            self.keys[action as usize] = keys.iter().fold(KeyState::default(), |state, key| state.with(*key));
+        }
+
+        pub fn keys(&self, action: Action) -> KeyState 
+        {
+            self.keys[action as usize]
+        }
+
+        pub fn is_down(&self, action: Action, input: KeyState) -> bool 
+        {
+            input.any_down(self.keys(action))
+        }
+    }
+
+    pub struct Game 
+    {
+        current_map: Map,
+        // Every level as it was loaded, current_map is a copy of the one being played
+        levels: Vec<Map>,
+        level: usize,
+        main_player: MainPlayer,
+        camera: Camera,
+        particles: Vec<Particle>,
+        // Distance to the wall drawn in every screen column, for depth testing in Mode3d
+        depth_buffer: Vec<f32>,
+        clock: Box<dyn Clock + Send>,
+        // Clock time of the previous frame_dt() call
+        last_frame: Duration,
+        paused: bool,
+        // Debounced, only the toggles look at the previous frame's keys
+        last_input: KeyState,
+        debounce: Debounce,
+        bindings: KeyBindings,
+        // Multiplies the scale at which the whole map fits the screen in the 2d views
+        map_zoom: f32,
+        rng: Rng,
+        fps: FpsCounter,
+        show_fps: bool,
+        show_compass: bool,
+        // Edges of the field of view in the 2d views, out to the visible distance
+        show_fov_cone: bool,
+        // Keep where every ray of the frame stopped, off unless debugging
+        collect_hits: bool,
+        last_frame_hits: Vec<RayHit>,
+        // The map views only show the cells the rays went through or stopped at
+        fog_of_war: bool,
+        // Cells seen so far, one list per level in the levels' cell order, kept when the level is left
+        explored: Vec<Vec<bool>>,
+        crosshair: Option<u8>,
+        // Text under the crosshair while something interact_range cells ahead can be used, None hides it
+        interact_prompt: Option<String>,
+        interact_range: f32,
+        // Terminal cell the compass text starts at
+        compass_position: Vec2<i32>,
+        // Moves the player straight through walls, for inspecting maps
+        noclip: bool,
+        // The walking and turning keys fly the camera instead of the player
+        spectating: bool,
+        // Whether N toggles noclip at all, on by default only in debug builds
+        allow_noclip: bool,
+        palette: Palette,
+        // Shade walls by the map's per cell lights, flat lit maps leave it off
+        lighting: bool,
+        // Brightness of walls hit on the y axis compared to the x axis ones, 
+        // so the two sides of a corner differ in color and not only in character
+        side_shade: f32,
+        // Walls fade out towards the visible distance
+        distance_fog: bool,
+        // Exponent on the brightness before it picks a shading character, 
+        // above 1 darkens the falloff and below 1 washes it out
+        gamma: f32,
+        // Multiplies the frame time of everything that moves, the HUD and fps counter keep real time
+        time_scale: f32,
+        tick_duration: Duration,
+        // World time since the last tick
+        tick_elapsed: Duration,
+        ticks_this_frame: u32,
+        tick_count: u64,
+        // Drives the player instead of the keyboard while set
+        autopilot: Option<Autopilot>,
+        messages: VecDeque<Message>,
+        // Drawn at the bottom of the 3d view when set
+        weapon: Option<Weapon>,
+        // Seconds the firing frame is still shown for
+        weapon_flash: f32,
+        // Walking distance driving the weapon's sway, in cells
+        weapon_bob: f32,
+        view_bob: bool,
+        view_bob_amplitude: f32,
+        view_bob_frequency: f32,
+        // 1 while walking, fades to 0 once the player stops, so the horizon eases back to the middle
+        view_bob_strength: f32,
+        screen_shake: bool,
+        // Cells the view jitters by at most, settles to 0 over time
+        shake: f32,
+        // This frame's jitter in cells, drawn from the seeded rng every step, so replays shake the same
+        shake_offset: Vec2<f32>,
+        // The sideways jitter as an angle, it depends on the screen's width
+        shake_yaw: f32,
+        // The HUD's text is formatted into this every frame, so drawing it doesn't allocate
+        hud_text: String,
+    }
+
+    // Characters of the walls, by the axis the ray hit them on
+    #[derive(Copy, Clone)]
+    pub struct Palette 
+    {
+        pub wall_x: u8,
+        pub wall_y: u8,
+    }
+
+    impl Palette 
+    {
+        pub const DEFAULT: Palette = Palette { wall_x: BLACK_BOX_CHAR, wall_y: STRIP_BOX_CHAR };
+    }
+
+    struct Actor 
+    {
+        position: Vec2<f32>,
+        yaw: f32,
+        movement_speed: f32,
+        rotation_speed: f32,
+    }
+
+    struct MainPlayer 
+    {
+        actor: Actor,
+    }
+
+    #[derive(Clone)]
+    struct Map 
+    {
+        topography: Vec<i32>,
+        cell_width: f32,
+        cell_height: f32,
+        topography_y: i32,
+        topography_x: i32,
+        // Materials without a texture are drawn with the plain wall characters
+        textures: HashMap<i32, Texture>,
+        // Materials cycling through several textures, these win over the still ones
+        animations: HashMap<i32, Animation>,
+        // Brightness of every cell from 0 to 1, same layout as topography
+        lights: Vec<f32>,
+        // Where the player starts on this map and which way they face
+        spawn: Vec2<f32>,
+        spawn_yaw: f32,
+        // Radius of the round walls, same layout as topography, None for every other cell. Their 
+        // cells count as walls everywhere but in march_ray(), which hits the circle instead of the 
+        // cell's borders
+        pillars: Vec<Option<f32>>,
+        // Cells from every cell to the closest wall, counted like a king moves, the map's edge 
+        // is a wall too. Every cell less than that away is open, so rays jump over them in one 
+        // step instead of one cast per cell. Building it is a breadth first pass that looks at 
+        // every cell and its 8 neighbors once, and it keeps an i32 per cell. That's cheaper than 
+        // a cast per cell, so on open maps it's paid back after the rays of a frame or two cross 
+        // the map's cell count. set_shape() builds it again, so editing a huge map cell by cell is slow
+        open_distances: Vec<i32>,
+    }
+
+    // Characters of a wall surface, u goes along the wall and v from its top to its bottom
+    #[derive(Clone)]
+    pub struct Texture 
+    {
+        width: usize,
+        height: usize,
+        data: Vec<u8>,
+    }
+
+    impl Texture 
+    {
+        // None when data doesn't hold exactly width * height characters
+        pub fn new(width: usize,
+                   height: usize,
+                   data: Vec<u8>) -> Option<Texture> 
+        {
+            if width == 0 || height == 0 || data.len() != width * height {
+                return None;
+            }
+
+            Some(Texture { width, height, data })
+        }
+
+        // Character art, frames split by empty lines, shorter rows are padded with spaces
+        fn from_text(text: &str) -> Result<Vec<Texture>, String> 
+        {
+            let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+
+            if let Some(row) = lines.iter().position(|line| !line.is_ascii()) {
+                return Err(format!("Texture art has to be ASCII, row {} isn't", row + 1));
+            }
+
+            let frames: Vec<Texture> = lines.split(|line| line.is_empty())
+                .filter(|frame| !frame.is_empty())
+                .map(|frame| {
+                    let width = frame.iter().map(|line| line.len()).max().unwrap_or(0);
+                    let data = frame.iter()
+                        .flat_map(|line| format!("{:<1$}", line, width).into_bytes())
+                        .collect();
+
+                    Texture::new(width, frame.len(), data)
+                })
+                .collect::<Option<Vec<Texture>>>()
+                .ok_or("Texture frames need at least one character")?;
+
+            if frames.is_empty() {
+                return Err("Texture art is empty".to_string());
+            }
+
+            Ok(frames)
+        }
+
+        // Every frame of the texture at path, more than one makes it animated
+        pub fn load(path: &str) -> Result<Vec<Texture>, String> 
+        {
+            let text = std::fs::read_to_string(path)
+                .map_err(|err| format!("Cannot read texture {}: {}", path, err))?;
+
+            Texture::from_text(&text).map_err(|err| format!("Texture {}: {}", path, err))
+        }
+
+        fn sample(&self,
+                  u: f32,
+                  v: f32) -> u8 
+        {
+            let x = ((u * self.width as f32) as usize).min(self.width - 1);
+            let y = ((v * self.height as f32) as usize).min(self.height - 1);
+
+            self.data[y * self.width + x]
+        }
+    }
+
+    // Textures shown one after another, each for ticks_per_frame ticks, then from the first again
+    #[derive(Clone)]
+    struct Animation 
+    {
+        frames: Vec<Texture>,
+        ticks_per_frame: u32,
+        frame: usize,
+        // Ticks the current frame has been shown for
+        ticks: u32,
+    }
+
+    impl Animation 
+    {
+        fn tick(&mut self) 
+        {
+            self.ticks += 1;
+
+            if self.ticks >= self.ticks_per_frame {
+                self.ticks = 0;
+                self.frame = (self.frame + 1) % self.frames.len();
+            }
+        }
+    }
+
+    impl Map 
+    {
+        // Err when topography doesn't hold exactly topography_x * topography_y cells
+        fn new(topography: Vec<i32>,
+               topography_x: i32,
+               topography_y: i32,
+               cell_width: f32,
+               cell_height: f32) -> Result<Map, String> 
+        {
+            if topography_x <= 0 || topography_y <= 0 || 
+               topography.len() != topography_x as usize * topography_y as usize 
+            {
+                return Err(format!("Map of {}x{} cells has {} cells of topography", 
+                                   topography_x, topography_y, topography.len()));
+            }
+
+            let mut map = Map {
+                topography,
+                cell_width,
+                cell_height,
+                topography_y,
+                topography_x,
+                textures: HashMap::new(),
+                animations: HashMap::new(),
+                lights: vec![1.; (topography_x * topography_y).max(0) as usize],
+                spawn: DEFAULT_SPAWN,
+                spawn_yaw: DEFAULT_SPAWN_YAW,
+                pillars: vec![None; (topography_x * topography_y).max(0) as usize],
+                open_distances: Vec::new(),
+            };
+
+            map.open_distances = map.measure_open_distances();
+            Ok(map)
+        }
+
+        // The frame an animated material is on, or its still texture
+        fn texture(&self,
+                   material: i32) -> Option<&Texture> 
+        {
+            match self.animations.get(&material) {
+                Some(animation) => animation.frames.get(animation.frame),
+                None => self.textures.get(&material),
+            }
+        }
+
+        // Breadth first from every wall at once, over all 8 neighbors
+        fn measure_open_distances(&self) -> Vec<i32> 
+        {
+            let mut distances = vec![i32::MAX; self.topography.len()];
+            let mut queue = VecDeque::new();
+
+            for y in 0..self.topography_y 
+            {
+                for x in 0..self.topography_x 
+                {
+                    if self.is_solid(x, y) {
+                        distances[self.index(x, y).unwrap()] = 0;
+                        queue.push_back(Vec2 { x, y });
+                    }
+                }
+            }
+
+            // Outside of the map is solid, so the open cells along the edge are next to a wall
+            for y in 0..self.topography_y 
+            {
+                for x in 0..self.topography_x 
+                {
+                    let on_edge = x == 0 || y == 0 || x == self.topography_x - 1 || y == self.topography_y - 1;
+                    let i = self.index(x, y).unwrap();
+
+                    if on_edge && distances[i] > 1 {
+                        distances[i] = 1;
+                        queue.push_back(Vec2 { x, y });
+                    }
+                }
+            }
+
+            while let Some(cell) = queue.pop_front() 
+            {
+                let next = distances[self.index(cell.x, cell.y).unwrap()] + 1;
+
+                for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] 
+                {
+                    if let Some(i) = self.index(cell.x + dx, cell.y + dy) {
+                        if distances[i] > next {
+                            distances[i] = next;
+                            queue.push_back(Vec2 { x: cell.x + dx, y: cell.y + dy });
+                        }
+                    }
+                }
+            }
+
+            distances
+        }
+
+        // 0 for walls and outside of the map
+        fn open_distance(&self,
+                         x: i32,
+                         y: i32) -> i32 
+        {
+            self.index(x, y).map_or(0, |i| self.open_distances[i])
+        }
+
+        fn with_square_cells(topography: Vec<i32>,
+                             topography_x: i32,
+                             topography_y: i32,
+                             sqare_width: f32) -> Result<Map, String> 
+        {
+            Map::new(topography, topography_x, topography_y, sqare_width, sqare_width)
+        }
+
+        // Text map, every line is a row of cells and every digit is the cell's material, 0 is empty 
+        // and 9 is the exit. An S is an empty cell the player starts in the middle of, an O a pillar
+        fn load(path: &str,
+                sqare_width: f32) -> Result<Map, String> 
+        {
+            let text = std::fs::read_to_string(path)
+                .map_err(|err| format!("Cannot read map {}: {}", path, err))?;
+
+            let mut topography = Vec::new();
+            let mut topography_x = 0;
+            let mut topography_y = 0;
+            let mut spawn = None;
+            let mut pillars = Vec::new();
+
+            for (i, line) in text.lines().map(str::trim_end).filter(|line| !line.is_empty()).enumerate() 
+            {
+                if topography_y > 0 && line.len() != topography_x {
+                    return Err(format!("Map {}: row {} is {} cells wide, expected {}", path, i + 1, line.len(), topography_x));
+                }
+
+                for (x, ch) in line.chars().enumerate() 
+                {
+                    match ch.to_digit(10) {
+                        Some(material) => topography.push(material as i32),
+                        None if ch == SPAWN_CELL => {
+                            spawn = Some(Vec2 { x: x as i32, y: topography_y });
+                            topography.push(0);
+                        }
+                        None if ch == PILLAR_CELL => {
+                            pillars.push(Vec2 { x: x as i32, y: topography_y });
+                            topography.push(1);
+                        }
+                        None => return Err(format!("Map {}: row {} has a bad cell '{}'", path, i + 1, ch)),
+                    }
+                }
+
+                topography_x = line.len();
+                topography_y += 1;
+            }
+
+            if topography.is_empty() {
+                return Err(format!("Map {} is empty", path));
+            }
+
+            let mut map = Map::with_square_cells(topography, topography_x as i32, topography_y, sqare_width)
+                .map_err(|err| format!("Map {}: {}", path, err))?;
+
+            if let Some(cell) = map.open_border_cell() {
+                return Err(format!("Map {}: row {} column {} is open, the border has to be walls all around", 
+                                   path, cell.y + 1, cell.x + 1));
+            }
+
+            for cell in pillars 
+            {
+                map.set_shape(cell.x, cell.y, CellShape::Pillar(DEFAULT_PILLAR_RADIUS))?;
+            }
+
+            // Without an S the default spawn can be anywhere, even in a wall, the closest open cell it is
+            map.spawn = match spawn {
+                Some(cell) => map.cell_center(cell),
+                None => map.nearest_open_cell(DEFAULT_SPAWN)
+                    .ok_or_else(|| format!("Map {} has no open cell to start in", path))?,
+            };
+
+            Ok(map)
+        }
+
+        // Rays and the player stay in a map that's walled in all around, exits don't close it
+        fn is_enclosed(&self) -> bool 
+        {
+            self.open_border_cell().is_none()
+        }
+
+        // First cell along the edge of the map that isn't solid, row by row
+        fn open_border_cell(&self) -> Option<Vec2<i32>> 
+        {
+            for y in 0..self.topography_y 
+            {
+                for x in 0..self.topography_x 
+                {
+                    let on_edge = x == 0 || y == 0 || x == self.topography_x - 1 || y == self.topography_y - 1;
+
+                    if on_edge && !self.is_solid(x, y) {
+                        return Some(Vec2 { x, y });
+                    }
+                }
+            }
+
+            None
+        }
+
+        // None for cells outside of the map
+        fn index(&self,
+                 x: i32,
+                 y: i32) -> Option<usize> 
+        {
+            if x < 0 || y < 0 || x >= self.topography_x || y >= self.topography_y {
+                return None;
+            }
+
+            Some((self.topography_x * y + x) as usize)
+        }
+
+        // Material of the cell, None outside of the map
+        fn get(&self,
+               x: i32,
+               y: i32) -> Option<i32> 
+        {
+            self.index(x, y).map(|i| self.topography[i])
+        }
+
+        // Walls stop rays and the player, cells outside of the map count as walls as well
+        fn is_solid(&self,
+                    x: i32,
+                    y: i32) -> bool 
+        {
+            match self.get(x, y) {
+                Some(material) => material != 0 && material != EXIT_CELL,
+                None => true,
+            }
+        }
+
+        // Outside of the map is a plain wall
+        fn shape(&self,
+                 x: i32,
+                 y: i32) -> CellShape 
+        {
+            if !self.is_solid(x, y) {
+                return CellShape::Empty;
+            }
+
+            match self.index(x, y).and_then(|i| self.pillars[i]) {
+                Some(radius) => CellShape::Pillar(radius),
+                None => CellShape::Wall,
+            }
+        }
+
+        // Walls keep their material, new ones are material 1. Err outside of the map
+        fn set_shape(&mut self,
+                     x: i32,
+                     y: i32,
+                     shape: CellShape) -> Result<(), String> 
+        {
+            let i = self.index(x, y)
+                .ok_or_else(|| format!("Cell {}, {} is outside of the map", x, y))?;
+
+            self.pillars[i] = None;
+
+            match shape 
+            {
+                CellShape::Empty => self.topography[i] = 0,
+                CellShape::Wall | CellShape::Pillar(_) if !self.is_solid(x, y) => self.topography[i] = 1,
+                _ => {}
+            }
+
+            if let CellShape::Pillar(radius) = shape {
+                self.pillars[i] = Some(radius.clamp(MIN_PILLAR_RADIUS, MAX_PILLAR_RADIUS));
+            }
+
+            self.open_distances = self.measure_open_distances();
+            Ok(())
+        }
+
+        // Where a pillar's surface faces at pos, None when pos isn't in a pillar's cell
+        fn pillar_normal(&self,
+                         pos: Vec2<f32>) -> Option<Vec2<f32>> 
+        {
+            let cell = self.cell_at(pos);
+            if !matches!(self.shape(cell.x, cell.y), CellShape::Pillar(_)) {
+                return None;
+            }
+
+            let center = self.cell_center(cell);
+            let offset = Vec2 { x: pos.x - center.x, y: pos.y - center.y };
+            let length = (offset.x * offset.x + offset.y * offset.y).sqrt();
+
+            // Only a camera standing right in the middle of one
+            if length <= 0. {
+                return Some(Axis::OnX.normal());
+            }
+
+            Some(Vec2 { x: offset.x / length, y: offset.y / length })
+        }
+
+        fn cell_at(&self,
+                   pos: Vec2<f32>) -> Vec2<i32> 
+        {
+            Vec2 {
+                x: (pos.x / self.cell_width).floor() as i32,
+                y: (pos.y / self.cell_height).floor() as i32,
+            }
+        }
+
+        // Center of the open cell closest to pos, None when the map is all walls
+        fn nearest_open_cell(&self,
+                             pos: Vec2<f32>) -> Option<Vec2<f32>> 
+        {
+            let mut nearest: Option<Vec2<f32>> = None;
+
+            for y in 0..self.topography_y 
+            {
+                for x in 0..self.topography_x 
+                {
+                    if self.is_solid(x, y) {
+                        continue;
+                    }
+
+                    let center = self.cell_center(Vec2 { x, y });
+                    if nearest.is_none_or(|n| points_distance(pos, center) < points_distance(pos, n)) {
+                        nearest = Some(center);
+                    }
+                }
+            }
+
+            nearest
+        }
+
+        fn light(&self,
+                 x: i32,
+                 y: i32) -> Option<f32> 
+        {
+            self.index(x, y).map(|i| self.lights[i])
+        }
+
+        fn cell_top_left(&self,
+                         cell: Vec2<i32>) -> Vec2<f32> 
+        {
+            Vec2 { x: cell.x as f32 * self.cell_width, y: cell.y as f32 * self.cell_height }
+        }
+
+        fn cell_center(&self,
+                       cell: Vec2<i32>) -> Vec2<f32> 
+        {
+            let top_left = self.cell_top_left(cell);
+
+            Vec2 { x: top_left.x + self.cell_width / 2., y: top_left.y + self.cell_height / 2. }
+        }
+
+        // Top left and bottom right corner
+        fn cell_bounds(&self,
+                       cell: Vec2<i32>) -> (Vec2<f32>, Vec2<f32>) 
+        {
+            let top_left = self.cell_top_left(cell);
+
+            (top_left, Vec2 { x: top_left.x + self.cell_width, y: top_left.y + self.cell_height })
+        }
+
+        // The longer side of a cell, the unit camera distances are measured in
+        fn cell_size(&self) -> f32 
+        {
+            self.cell_width.max(self.cell_height)
+        }
+    }
+
+    // Rays stop at the first wall, or when they get further than max_visible_distance squares 
+    // (times the map's cell_size() in world units) from the camera, or after crossing max_steps squares,
+    // whichever comes first. Raise max_steps for big open maps, the distance is what limits the view
+    struct Camera
+    {
+        max_visible_distance: i32,
+        max_steps: i32,
+        fov: f32,
+        // Looking up and down shears the picture by moving the horizon, nothing gets
+        // rotated like with a true pitch, so walls stay vertical. Fraction of the screen height, 
+        // positive looks up
+        vertical_look: f32,
+        cell_aspect: f32,
+        // Degrees from the top of the screen to the bottom, 0 derives it from fov so walls stay 
+        // cubes. Smaller zooms in on the walls' height, bigger shows more of them, fov is kept
+        vertical_fov: f32,
+        // How far past a square's border a ray is pushed, as a fraction of the cell size. Too small 
+        // and f32 rounding can leave the ray on the border, stuck in the same square until 
+        // max_steps runs out and the wall behind it disappears. Too big and rays jump 
+        // over the corners of walls, which shows as holes along wall edges
+        boundary_epsilon: f32,
+        // One ray is cast for every column_step columns and its strip is repeated over them. 
+        // Casting is most of the frame, so 2 is about twice as fast, at half the horizontal 
+        // resolution, walls get blocky edges and thin gaps between them can disappear
+        column_step: u8,
+        // Rays cast over the whole fov, fov / ray_count apart, 0 leaves it to column_step. 
+        // At the screen width in columns that's one ray per column, below it every ray covers 
+        // width / ray_count columns and above it the rays landing in the same column are averaged 
+        // into it, which smooths wall edges and the shading at the cost of casting more
+        ray_count: u32,
+        // Where the view is cast from and which way it faces, everything drawn reads these 
+        // and not the player
+        position: Vec2<f32>,
+        yaw: f32,
+        // Put at the player's eyes before every frame. Off while spectating or while something 
+        // else placed the camera, then the player is drawn like anything else in the world
+        follows_player: bool,
+    }
+
+    impl Camera 
+    {
+        // Screen column of a world point and its perpendicular distance from the camera, None when 
+        // it's out of the fov or behind the camera. Columns split the fov into equal angles, 
+        // the same way the rays are spread over the screen
+        pub fn project_point(&self,
+                             position: Vec2<f32>,
+                             yaw: f32,
+                             target: Vec2<f32>,
+                             screen_width: i16) -> Option<(i16, f32)> 
+        {
+            let to_target = Vec2 { x: target.x - position.x, y: target.y - position.y };
+            let half_fov = self.fov / 2. * RADIAN;
+            // Angle between the facing direction and the target, wrapped to -PI..PI
+            let offset = normalize_angle(to_target.angle() - yaw + PI) - PI;
+            let perpendicular = to_target.dot(Vec2::from_angle(yaw));
+
+            if offset.abs() > half_fov || perpendicular <= 0. || screen_width <= 0 {
+                return None;
+            }
+
+            let column = ((offset + half_fov) / (2. * half_fov) * screen_width as f32) as i16;
+
+            Some((column.min(screen_width - 1), perpendicular))
+        }
+    }
+
+    // World to screen transform of the 2d views, keeps the player in the middle of the screen
+    struct MapView 
+    {
+        scale: f32,
+        focus: Vec2<f32>,
+        screen_center: Vec2<f32>,
+    }
+
+    impl MapView 
+    {
+        fn apply(&self,
+                 pos: Vec2<f32>) -> Vec2<f32> 
+        {
+            Vec2 {
+                x: (pos.x - self.focus.x) * self.scale + self.screen_center.x,
+                y: (pos.y - self.focus.y) * self.scale + self.screen_center.y,
+            }
+        }
+    }
+
+    // Xorshift64, only reproducibility matters here, not the quality of the numbers
+    struct Rng 
+    {
+        state: u64,
+    }
+
+    impl Rng 
+    {
+        fn new(seed: u64) -> Rng 
+        {
+            // Zero would get xorshift stuck at zero forever
+            Rng { state: if seed == 0 { DEFAULT_SEED } else { seed } }
+        }
+
+        fn next_u64(&mut self) -> u64 
+        {
+            self.state ^= self.state << 13;
+            self.state ^= self.state >> 7;
+            self.state ^= self.state << 17;
+            self.state
+        }
+
+        // Uniform in [0, 1)
+        fn next_f32(&mut self) -> f32 
+        {
+            (self.next_u64() >> 40) as f32 / (1_u64 << 24) as f32
+        }
+
+        fn range(&mut self,
+                 min: f32,
+                 max: f32) -> f32 
+        {
+            min + (max - min) * self.next_f32()
+        }
+    }
+
+    // Where the game takes the time from, so the frame time can come from somewhere else than the system
+    pub trait Clock 
+    {
+        // Time since some fixed point, only the difference between two calls means anything
+        fn now(&self) -> Duration;
+    }
+
+    pub struct SystemClock 
+    {
+        start: Instant,
+    }
+
+    impl Default for SystemClock 
+    {
+        fn default() -> SystemClock 
+        {
+            SystemClock::new()
+        }
+    }
+
+    impl SystemClock 
+    {
+        pub fn new() -> SystemClock 
+        {
+            SystemClock { start: Instant::now() }
+        }
+    }
+
+    impl Clock for SystemClock 
+    {
+        fn now(&self) -> Duration 
+        {
+            self.start.elapsed()
+        }
+    }
+
+    // Stands still until advanced by hand. Clones share the time, so a clone can be handed 
+    // to the game and the original kept to move the time forward
+    #[derive(Clone, Default)]
+    pub struct MockClock 
+    {
+        nanos: Arc<AtomicU64>,
+    }
+
+    impl MockClock 
+    {
+        pub fn new() -> MockClock 
+        {
+            MockClock::default()
+        }
+
+        pub fn advance(&self,
+                       by: Duration) 
+        {
+            self.nanos.fetch_add(by.as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+
+    impl Clock for MockClock 
+    {
+        fn now(&self) -> Duration 
+        {
+            Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+        }
+    }
+
+    // Rolling average over the last FPS_SAMPLES frame times
+    struct FpsCounter 
+    {
+        samples: [f32; FPS_SAMPLES],
+        next: usize,
+        count: usize,
+    }
+
+    impl FpsCounter 
+    {
+        fn new() -> FpsCounter 
+        {
+            FpsCounter {
+                samples: [0.; FPS_SAMPLES],
+                next: 0,
+                count: 0,
+            }
+        }
+
+        fn push(&mut self,
+                frame_time: f32) 
+        {
+            self.samples[self.next] = frame_time;
+            self.next = (self.next + 1) % FPS_SAMPLES;
+            self.count = (self.count + 1).min(FPS_SAMPLES);
+        }
+
+        fn average(&self) -> f32 
+        {
+            let total: f32 = self.samples.iter().sum();
+
+            if total <= 0. {
+                return 0.;
+            }
+
+            self.count as f32 / total
+        }
+    }
+
+    struct Particle 
+    {
+        pos: Vec2<f32>,
+        vel: Vec2<f32>,
+        life: f32,
+        ch: u8,
+    }
+
+    struct Message 
+    {
+        text: String,
+        // Seconds left before it's dropped from the log
+        life: f32,
+    }
+
+    // Character art of the held weapon, an idle frame and one shown right after a shot. 
+    // Rows are width cells long, transparent cells show the scene behind them
+    pub struct Weapon 
+    {
+        width: i16,
+        idle: Vec<u8>,
+        firing: Vec<u8>,
+    }
+
+    impl Weapon 
+    {
+        // None when the frames differ in size or don't split into whole rows
+        pub fn new(width: i16,
+                   idle: Vec<u8>,
+                   firing: Vec<u8>) -> Option<Weapon> 
+        {
+            if width <= 0 || idle.is_empty() || idle.len() != firing.len() || !idle.len().is_multiple_of(width as usize) {
+                return None;
+            }
+
+            Some(Weapon { width, idle, firing })
+        }
+
+        // The idle frame, an empty line and the firing frame. Spaces are see through 
+        // and short rows are padded with them. ASCII only, a screen cell holds one byte
+        fn from_text(text: &str) -> Result<Weapon, String> 
+        {
+            let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+
+            if let Some(row) = lines.iter().position(|line| !line.is_ascii()) {
+                return Err(format!("Weapon art has to be ASCII, row {} isn't", row + 1));
+            }
+            let split = lines.iter().position(|line| line.is_empty())
+                .ok_or("Weapon art needs an empty line between the idle and the firing frame")?;
+
+            let (idle, firing) = (&lines[..split], &lines[split + 1..]);
+            let firing = &firing[..firing.iter().rposition(|line| !line.is_empty()).map_or(0, |last| last + 1)];
+
+            if idle.len() != firing.len() {
+                return Err(format!("Weapon frames have to be as tall, got {} and {} rows", idle.len(), firing.len()));
+            }
+
+            let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+            let cells = |frame: &[&str]| -> Vec<u8> {
+                frame.iter()
+                    .flat_map(|line| format!("{:<1$}", line, width).into_bytes())
+                    .map(|ch| if ch == b' ' { TRANSPARENT_CHAR } else { ch })
+                    .collect()
+            };
+
+            Weapon::new(width as i16, cells(idle), cells(firing)).ok_or_else(|| "Weapon art is empty".to_string())
+        }
+
+        pub fn load(path: &str) -> Result<Weapon, String> 
+        {
+            let text = std::fs::read_to_string(path)
+                .map_err(|err| format!("Cannot read weapon {}: {}", path, err))?;
+
+            Weapon::from_text(&text).map_err(|err| format!("Weapon {}: {}", path, err))
+        }
+
+        fn height(&self) -> i32 
+        {
+            (self.idle.len() / self.width as usize) as i32
+        }
+    }
+
+    // Walks the player through the waypoints in a loop, for demos and idle screens
+    struct Autopilot 
+    {
+        waypoints: Vec<Vec2<f32>>,
+        next: usize,
+        // Seconds spent heading to the next waypoint, one behind a wall is given up on eventually
+        elapsed: f32,
+    }
+
+    impl Default for Game 
+    {
+        fn default() -> Game 
+        {
+            Game::new()
+        }
+    }
+
+    impl Game 
+    {
+        pub fn new() -> Game 
+        {
+            let new_main_player = MainPlayer {
+                actor: Actor {
+                    position: DEFAULT_SPAWN,
+                    yaw: DEFAULT_SPAWN_YAW,
+                    movement_speed: PLAYER_MOVEMENT_SPEED,
+                    rotation_speed: PLAYER_ROTATION_SPEED,
+                }
+            };
+            
+            let new_map = Map::with_square_cells(
+                [
+                  1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+                  1, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+                  1, 0, 0, 1, 1, 0, 0, 0, 0, 1,
+                  1, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+                  1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 
+                  1, 0, 0, 0, 0, 0, 0, 1, 0, 1, 
+                  1, 0, 0, 0, 0, 0, 0, 1, 1, 1, 
+                  1, 0, 0, 0, 0, 0, 0, 1, 1, 1, 
+                  1, 0, 0, 0, 0, 0, 0, 1, 1, 1, 
+                  1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 
+                ]
+                .to_vec(),
+                10,
+                10,
+                BUILT_IN_CELL_SIZE)
+                .expect("The built in map has as many cells as its size");
+
+            assert!(!new_map.is_solid(DEFAULT_SPAWN_CELL.x, DEFAULT_SPAWN_CELL.y), 
+                    "The built in map's spawn cell {}, {} is a wall", 
+                    DEFAULT_SPAWN_CELL.x, 
+                    DEFAULT_SPAWN_CELL.y);
+            assert!(new_map.is_enclosed(), "The built in map's border has a hole");
+
+            let new_camera = Camera {
+                max_visible_distance: DEFAULT_VISIBLE_DISTANCE,
+                max_steps: 64,
+                fov: DEFAULT_FOV,
+                vertical_look: 0.,
+                cell_aspect: DEFAULT_CELL_ASPECT,
+                vertical_fov: DEFAULT_VERTICAL_FOV,
+                boundary_epsilon: DEFAULT_BOUNDARY_EPSILON,
+                column_step: 1,
+                ray_count: 0,
+                position: new_map.spawn,
+                yaw: new_map.spawn_yaw,
+                follows_player: true,
+            };
+
+            Game {
+                levels: vec![new_map.clone()],
+                level: 0,
+                current_map: new_map,
+                main_player: new_main_player,
+                camera: new_camera,
+                particles: Vec::new(),
+                depth_buffer: Vec::new(),
+                clock: Box::new(SystemClock::new()),
+                last_frame: Duration::ZERO,
+                paused: false,
+                last_input: KeyState::default(),
+                debounce: Debounce::new(Duration::ZERO),
+                bindings: KeyBindings::default(),
+                map_zoom: 1.,
+                rng: Rng::new(DEFAULT_SEED),
+                fps: FpsCounter::new(),
+                show_fps: false,
+                show_compass: false,
+                show_fov_cone: false,
+                collect_hits: false,
+                last_frame_hits: Vec::new(),
+                fog_of_war: false,
+                explored: Vec::new(),
+                crosshair: Some(CROSSHAIR_CHAR),
+                interact_prompt: Some(DEFAULT_INTERACT_PROMPT.to_string()),
+                interact_range: DEFAULT_INTERACT_RANGE,
+                compass_position: DEFAULT_COMPASS_POSITION,
+                noclip: false,
+                spectating: false,
+                allow_noclip: cfg!(debug_assertions),
+                palette: Palette::DEFAULT,
+                lighting: false,
+                side_shade: DEFAULT_SIDE_SHADE,
+                distance_fog: true,
+                gamma: DEFAULT_GAMMA,
+                time_scale: DEFAULT_TIME_SCALE,
+                tick_duration: TICK_DURATION,
+                tick_elapsed: Duration::ZERO,
+                ticks_this_frame: 0,
+                tick_count: 0,
+                autopilot: None,
+                messages: VecDeque::new(),
+                weapon: Weapon::from_text(DEFAULT_WEAPON_ART).ok(),
+                weapon_flash: 0.,
+                weapon_bob: 0.,
+                view_bob: false,
+                view_bob_amplitude: DEFAULT_VIEW_BOB_AMPLITUDE,
+                view_bob_frequency: DEFAULT_VIEW_BOB_FREQUENCY,
+                view_bob_strength: 0.,
+                screen_shake: true,
+                shake: 0.,
+                shake_offset: Vec2 { x: 0., y: 0. },
+                shake_yaw: 0.,
+                hud_text: String::new(),
+            }
+        }
+
+        pub fn with_config(config: &Config) -> Result<Game, String> 
+        {
+            let mut game = Game::with_seed(config.seed);
+
+            game.main_player.actor.movement_speed = config.movement_speed;
+            game.main_player.actor.rotation_speed = config.rotation_speed;
+            game.camera.fov = config.fov;
+            game.set_cell_aspect(config.cell_aspect);
+            game.set_vertical_fov(config.vertical_fov);
+            game.set_column_step(config.column_step);
+            game.set_ray_count(config.ray_count);
+            game.set_gamma(config.gamma);
+            game.set_time_scale(config.time_scale);
+            game.set_tick_duration(Duration::from_millis(config.tick_ms));
+            game.set_debounce(Duration::from_millis(config.debounce_ms));
+            game.set_view_bob(config.view_bob, config.view_bob_amplitude, config.view_bob_frequency);
+            game.set_screen_shake(config.screen_shake);
+            game.set_boundary_epsilon(config.boundary_epsilon);
+            game.camera.max_visible_distance = config.max_visible_distance;
+            game.bindings = config.bindings;
+            game.show_fps = config.show_fps;
+            game.set_compass(config.show_compass, config.compass_position);
+            game.set_show_fov_cone(config.show_fov_cone);
+            game.set_collect_hits(config.debug_hits);
+            game.set_fog_of_war(config.fog_of_war);
+            game.set_crosshair(Some(config.crosshair_char).filter(|_| config.show_crosshair));
+            game.set_interact_prompt(Some(config.interact_prompt.clone()).filter(|prompt| !prompt.is_empty()), 
+                                     config.interact_range);
+
+            let cell_size = game.current_map.cell_size();
+            if let Some(path) = &config.map {
+                game.levels = vec![Map::load(path, cell_size)?];
+            }
+
+            for path in config.levels.iter() 
+            {
+                game.levels.push(Map::load(path, cell_size)?);
+            }
+
+            game.load_level(0)?;
+
+            if config.spawn.is_some() || config.spawn_yaw.is_some() {
+                let spawn = config.spawn.unwrap_or(game.current_map.spawn);
+                let yaw = config.spawn_yaw.map_or(game.current_map.spawn_yaw, |degrees| degrees * RADIAN);
+
+                game.set_spawn(spawn, yaw).map_err(|err| format!("spawn in the config: {}", err))?;
+            }
+            game.set_autopilot(config.autopilot.clone());
+
+            if !config.show_weapon {
+                game.set_weapon(None);
+            } else if let Some(path) = &config.weapon {
+                game.set_weapon(Some(Weapon::load(path)?));
+            }
+
+            for setting in config.textures.iter() 
+            {
+                let mut frames = Texture::load(&setting.path)?;
+
+                if frames.len() == 1 {
+                    game.set_texture(setting.material, frames.remove(0));
+                } else {
+                    game.set_animated_texture(setting.material, frames, setting.ticks_per_frame)?;
+                }
+            }
+
+            Ok(game)
+        }
+
+        // Same seed, same inputs, same game, everything random goes through this rng
+        pub fn with_seed(seed: u64) -> Game 
+        {
+            let mut game = Game::new();
+            game.rng = Rng::new(seed);
+            game
+        }
+
+        pub fn update(&mut self,
+                      output: &mut Renderer,
+                      input: KeyState,
+                      mode: ViewMode) 
+        {
+            let dt = self.frame_dt();
+            self.update_with_dt(output, input, mode, dt);
+        }
+
+        // Seconds since the previous call, measured every frame, paused or not, 
+        // so unpausing doesn't show up as one huge frame
+        pub fn frame_dt(&mut self) -> f32 
+        {
+            let now = self.clock.now();
+            // A clock that went backwards counts as no time at all
+            let dt = now.saturating_sub(self.last_frame).as_secs_f32();
+            self.last_frame = now;
+
+            dt
+        }
+
+        // The next frame_dt() measures from the new clock's current time
+        pub fn set_clock(&mut self,
+                         clock: Box<dyn Clock + Send>) 
+        {
+            self.last_frame = clock.now();
+            self.clock = clock;
+        }
+
+        // Same as update() with the frame time given from outside, for replays
+        pub fn update_with_dt(&mut self,
+                              output: &mut Renderer,
+                              input: KeyState,
+                              mode: ViewMode,
+                              dt: f32) 
+        {
+            self.step(input, dt);
+            self.render(output, mode);
+        }
+
+        // Advances the game by dt seconds of input without drawing anything
+        pub fn step(&mut self,
+                    input: KeyState,
+                    dt: f32) 
+        {
+            let last_input = self.last_input;
+            let debounced = self.debounce.filter(input, dt);
+            self.last_input = debounced;
+
+            for action in TOGGLE_ACTIONS 
+            {
+                if self.bindings.is_down(action, debounced) && !self.bindings.is_down(action, last_input) {
+                    self.toggle(action);
+                }
+            }
+
+            self.advance(input, dt);
+        }
+
+        // Like step(), with the toggles going by events instead of what changed since the last frame. 
+        // Every press counts, in order, even one that went down and up between two frames, the held 
+        // actions still go by input. The events aren't debounced here, see Debounce::filter_events()
+        pub fn step_with_events(&mut self,
+                                input: KeyState,
+                                events: &[KeyEvent],
+                                dt: f32) 
+        {
+            // Kept up to date for a step() after this one
+            self.last_input = self.debounce.filter(input, dt);
+
+            for event in events.iter().filter(|event| event.down) 
+            {
+                let key = KeyState::default().with(event.key);
+
+                for action in TOGGLE_ACTIONS 
+                {
+                    if self.bindings.is_down(action, key) {
+                        self.toggle(action);
+                    }
+                }
+            }
+
+            self.advance(input, dt);
+        }
+
+        fn toggle(&mut self,
+                  action: Action) 
+        {
+            match action {
+                Action::Pause => self.paused = !self.paused,
+                Action::Noclip if self.allow_noclip => self.noclip = !self.noclip,
+                Action::ColumnStep => self.camera.column_step = self.camera.column_step % MAX_COLUMN_STEP + 1,
+                Action::Spectate => self.set_spectating(!self.spectating),
+                _ => {}
+            }
+        }
+
+        // Everything in a step that goes on for as long as keys are held, and the world's time
+        fn advance(&mut self,
+                   input: KeyState,
+                   dt: f32) 
+        {
+            let bindings = self.bindings;
+            let world_dt = dt * self.time_scale;
+
+            if !self.paused && self.spectating 
+            {
+                self.fly_spectator(input, world_dt);
+            }
+            else if !self.paused 
+            {
+                let start = self.main_player.actor.position;
+
+                // Only pausing is left to the keyboard while the autopilot drives
+                let input = match self.autopilot {
+                    Some(_) => {
+                        self.follow_autopilot(world_dt);
+                        KeyState::default()
+                    }
+                    None => input,
+                };
+
+                // Every held key counts, so moving and turning at once works
+                let yaw = self.main_player.actor.yaw;
+
+                if bindings.is_down(Action::Forward, input) {
+                    self.move_player(yaw, world_dt);
+                }
+                    
+                if bindings.is_down(Action::StrafeRight, input) {
+                    self.move_player(normalize_angle(yaw + HALF_PI), world_dt);
+                }
+
+                if bindings.is_down(Action::Back, input) {
+                    self.move_player(normalize_angle(yaw + PI), world_dt);
+                }
+
+                if bindings.is_down(Action::StrafeLeft, input) {
+                    self.move_player(normalize_angle(yaw + PI + HALF_PI), world_dt);
+                }
+
+                let walked = points_distance(start, self.main_player.actor.position);
+                self.weapon_bob += walked / self.current_map.cell_size();
+
+                let fade = if walked > 0. { world_dt } else { -world_dt };
+                self.view_bob_strength = (self.view_bob_strength + fade / VIEW_BOB_FADE).clamp(0., 1.);
+
+                // The last level's exits lead nowhere
+                if self.material_at(self.main_player.actor.position) == EXIT_CELL && self.level + 1 < self.levels.len() {
+                    let _ = self.load_level(self.level + 1);
+                    self.push_message(format!("Level {}", self.level + 1));
+                }
+
+                // Per second like walking, so neither depends on the frame rate
+                if bindings.is_down(Action::TurnRight, input) {
+                    self.main_player.actor.yaw += self.main_player.actor.rotation_speed * world_dt;
+                }
+
+                if bindings.is_down(Action::TurnLeft, input) {
+                    self.main_player.actor.yaw -= self.main_player.actor.rotation_speed * world_dt;
+                }
+
+                self.main_player.actor.yaw = normalize_angle(self.main_player.actor.yaw);
+
+                if bindings.is_down(Action::LookUp, input) {
+                    self.camera.vertical_look += VERTICAL_LOOK_SPEED * world_dt;
+                }
+
+                if bindings.is_down(Action::LookDown, input) {
+                    self.camera.vertical_look -= VERTICAL_LOOK_SPEED * world_dt;
+                }
+
+                self.camera.vertical_look = self.camera.vertical_look.clamp(-MAX_VERTICAL_LOOK, MAX_VERTICAL_LOOK);
+
+                if bindings.is_down(Action::Fire, input) {
+                    self.fire();
+                }
+            }
+
+            self.sync_camera();
+            if self.fog_of_war {
+                self.explore();
+            }
+            self.fps.push(dt);
+            self.ticks_this_frame = 0;
+
+            if !self.paused {
+                self.advance_ticks(world_dt);
+                self.update_particles(world_dt);
+                self.update_messages(dt);
+                self.weapon_flash = (self.weapon_flash - world_dt).max(0.);
+                self.update_shake(world_dt);
+            }
+        }
+
+        // Draws the game as it is, the view and the HUD. The game doesn't change, the depth buffer 
+        // and the last frame's hits are all a render leaves behind, so the same state can be drawn 
+        // any number of times. What the player has seen is marked in step()
+        pub fn render(&mut self,
+                      output: &mut Renderer,
+                      mode: ViewMode) 
+        {
+            self.calculate_and_draw(output, &mode);
+
+            // The weapon is in the player's hands, it goes with them when the camera doesn't
+            if mode == ViewMode::Mode3d && self.camera.follows_player {
+                self.draw_weapon(output);
+            }
+
+            if self.paused 
+            {
+                const PAUSED_BANNER: &str = " PAUSED ";
+                let cells = output.get_cell_dim();
+
+                output.draw_string(Vec2 { 
+                                       x: (cells.x as i32 - PAUSED_BANNER.len() as i32) / 2,
+                                       y: cells.y as i32 / 2,
+                                   },
+                                   PAUSED_BANNER);
+            }
+
+            if self.noclip {
+                output.draw_string(Vec2 { x: 0, y: 1 }, " NOCLIP ");
+            }
+
+            if self.spectating {
+                output.draw_string(Vec2 { x: 0, y: 2 }, " SPECTATOR ");
+            }
+
+            // About what the player faces, so not while the camera is elsewhere
+            if self.camera.follows_player && !self.paused && self.interact_target().is_some() 
+            {
+                if let Some(prompt) = &self.interact_prompt 
+                {
+                    let cells = output.get_cell_dim();
+
+                    self.hud_text.clear();
+                    let _ = write!(self.hud_text, " {} ", prompt);
+                    output.draw_string(Vec2 { 
+                                           x: (cells.x as i32 - self.hud_text.len() as i32) / 2,
+                                           y: cells.y as i32 / 2 + 2,
+                                       },
+                                       &self.hud_text);
+                }
+            }
+
+            self.draw_messages(output);
+
+            if self.show_compass 
+            {
+                let yaw = normalize_angle(self.camera.yaw);
+
+                self.hud_text.clear();
+                let _ = write!(self.hud_text, " {:<2} {:03.0} ", compass_label(yaw), yaw * DEGREE);
+                output.draw_string(self.compass_position, &self.hud_text);
+            }
+
+            if self.show_fps 
+            {
+                let cells = output.get_cell_dim();
+
+                self.hud_text.clear();
+                let _ = write!(self.hud_text, "{:5.1} FPS", self.fps.average());
+                output.draw_string(Vec2 { x: cells.x as i32 - self.hud_text.len() as i32, y: 0 }, &self.hud_text);
+            }
+        }
+
+        // One frame of the 3d view as width x height cells of text, rows split by newlines. 
+        // Renders off screen, the console is left alone, and game time doesn't advance, 
+        // nothing moves and the frame timer isn't touched
+        pub fn render_to_string(&mut self,
+                                width: i16,
+                                height: i16) -> String 
+        {
+            let mut output = Renderer::with_writer(Box::new(std::io::sink()));
+            output.set_dimensions(width, height.saturating_mul(2));
+            output.update();
+
+            self.calculate_and_draw(&mut output, &ViewMode::Mode3d);
+            if self.camera.follows_player {
+                self.draw_weapon(&mut output);
+            }
+            output.render();
+
+            let frame = output.snapshot();
+            if frame.width() <= 0 {
+                return String::new();
+            }
+
+            frame.data()
+                .chunks(frame.width() as usize)
+                .map(|row| String::from_utf8_lossy(row).into_owned())
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+
+        // Bottom center, swaying in a figure of eight while the player walks
+        fn draw_weapon(&self,
+                       output: &mut Renderer) 
+        {
+            let weapon = match &self.weapon {
+                Some(weapon) => weapon,
+                None => return,
+            };
+
+            let phase = self.weapon_bob / WEAPON_BOB_LENGTH * PI;
+            let cells = output.get_cell_dim();
+            let top_left = Vec2 {
+                x: (cells.x as i32 - weapon.width as i32) / 2 + (phase.sin() * WEAPON_SWAY).round() as i32,
+                y: cells.y as i32 - weapon.height() - ((2. * phase).sin().abs() * WEAPON_BOB).round() as i32,
+            };
+            let frame = if self.weapon_flash > 0. { &weapon.firing } else { &weapon.idle };
+
+            output.set_color(DEFAULT_COLOR);
+            output.blit(top_left, frame, weapon.width);
+        }
+
+        // None hides the weapon
+        pub fn set_weapon(&mut self,
+                          weapon: Option<Weapon>) 
+        {
+            self.weapon = weapon;
+        }
+
+        // Shown at the bottom of the screen for a few seconds
+        pub fn push_message(&mut self,
+                            text: String) 
+        {
+            if self.messages.len() == MESSAGE_CAPACITY {
+                self.messages.pop_front();
+            }
+
+            self.messages.push_back(Message { text, life: MESSAGE_LIFE });
+        }
+
+        fn update_messages(&mut self,
+                           dt: f32) 
+        {
+            for message in self.messages.iter_mut() 
+            {
+                message.life -= dt;
+            }
+
+            self.messages.retain(|message| message.life > 0.);
+        }
+
+        // Newest on the bottom row, older ones above it
+        fn draw_messages(&mut self,
+                         output: &mut Renderer) 
+        {
+            let bottom = output.get_cell_dim().y as i32 - 1;
+
+            for (row, message) in self.messages.iter().rev().take(MESSAGES_SHOWN).enumerate() 
+            {
+                self.hud_text.clear();
+                let _ = write!(self.hud_text, " {} ", message.text);
+
+                output.set_color(Color::WHITE.scale(message.life.min(1.)));
+                output.draw_string(Vec2 { x: 0, y: bottom - row as i32 }, &self.hud_text);
+            }
+
+            output.set_color(DEFAULT_COLOR);
+        }
+
+        // How far the eye is from the projection plane, in screen rows, which are half cells. 
+        // A wall of height h at distance d is h * distance / d rows tall, so this is the one 
+        // number behind the vertical scale. With a vertical fov the screen height h spans it
+        //
+        //     distance = (h / 2) / tan(vertical_fov / 2)
+        //
+        // Without one walls are cubes, projected the way the columns are. The rays span the fov 
+        // across the screen width w, which puts the plane (w / 2) / tan(fov / 2) columns away, 
+        // and one row is cell_aspect / 2 columns tall
+        //
+        //     distance = (w / 2) / tan(fov / 2) * 2 / cell_aspect
+        //
+        // With the height derived from the width a cube keeps its shape on any terminal, 
+        // wide ones just see more of the walls' height
+        fn projection_plane_distance(&self,
+                                     screen: &Vec2<i16>) -> f32 
+        {
+            if self.camera.vertical_fov > 0. 
+            {
+                let half_fov = (self.camera.vertical_fov / 2. * RADIAN).min(MAX_PROJECTED_HALF_FOV);
+
+                return screen.y as f32 / 2. / half_fov.tan();
+            }
+
+            let half_fov = (self.camera.fov / 2. * RADIAN).min(MAX_PROJECTED_HALF_FOV);
+            let focal = screen.x as f32 / 2. / half_fov.tan();
+
+            focal * 2. / self.camera.cell_aspect
+        }
+
+        // Rows a wall strip is tall times its distance, wall_strip() divides by the distance
+        fn projection_scale(&self,
+                            screen: &Vec2<i16>) -> f32 
+        {
+            self.current_map.cell_size() * self.projection_plane_distance(screen)
+        }
+
+        // Turns towards the next waypoint and walks once it's roughly ahead. Reached or 
+        // timed out waypoints hand over to the one after, the last one back to the first
+        fn follow_autopilot(&mut self,
+                            dt: f32) 
+        {
+            let arrive_radius = (self.main_player.actor.movement_speed * dt).max(self.current_map.cell_size() / 4.);
+            let position = self.main_player.actor.position;
+
+            let target = match self.autopilot.as_mut() {
+                Some(autopilot) => {
+                    autopilot.elapsed += dt;
+
+                    let target = autopilot.waypoints[autopilot.next];
+                    if points_distance(position, target) <= arrive_radius || autopilot.elapsed > WAYPOINT_TIMEOUT {
+                        autopilot.next = (autopilot.next + 1) % autopilot.waypoints.len();
+                        autopilot.elapsed = 0.;
+                    }
+
+                    autopilot.waypoints[autopilot.next]
+                }
+                None => return,
+            };
+
+            let heading = Vec2 { x: target.x - position.x, y: target.y - position.y }.angle();
+            // Shortest way around, from -PI to PI
+            let turn = normalize_angle(heading - self.main_player.actor.yaw + PI) - PI;
+            let max_turn = self.main_player.actor.rotation_speed * dt;
+
+            self.main_player.actor.yaw = normalize_angle(self.main_player.actor.yaw + turn.clamp(-max_turn, max_turn));
+
+            if turn.abs() < AUTOPILOT_WALK_ANGLE {
+                self.move_player(self.main_player.actor.yaw, dt);
+            }
+        }
+
+        // An empty path turns the autopilot off and gives the player back to the keyboard
+        pub fn set_autopilot(&mut self,
+                             waypoints: Vec<Vec2<f32>>) 
+        {
+            self.autopilot = match waypoints.is_empty() {
+                true => None,
+                false => Some(Autopilot { waypoints, next: 0, elapsed: 0. }),
+            };
+        }
+
+        // Starts the level over from its spawn, whatever happened on it before is forgotten
+        pub fn load_level(&mut self,
+                          index: usize) -> Result<(), String> 
+        {
+            let map = self.levels.get(index)
+                .ok_or_else(|| format!("There is no level {}, only {}", index, self.levels.len()))?;
+
+            self.current_map = map.clone();
+            self.level = index;
+            self.main_player.actor.position = map.spawn;
+            self.main_player.actor.yaw = map.spawn_yaw;
+            self.camera.vertical_look = 0.;
+            self.follow_player();
+            self.particles.clear();
+
+            Ok(())
+        }
+
+        // Where the current level starts, moves the player there too. 
+        // Err when pos is outside of the map, in a wall or on an exit
+        pub fn set_spawn(&mut self,
+                         pos: Vec2<f32>,
+                         yaw: f32) -> Result<(), String> 
+        {
+            let cell = self.current_map.cell_at(pos);
+
+            if self.current_map.is_solid(cell.x, cell.y) {
+                return Err(format!("Spawn {}, {} is in a wall or outside of the map", pos.x, pos.y));
+            }
+            // The level would be over before it started
+            if self.current_map.get(cell.x, cell.y) == Some(EXIT_CELL) {
+                return Err(format!("Spawn {}, {} is on an exit", pos.x, pos.y));
+            }
+
+            let yaw = normalize_angle(yaw);
+            for map in [&mut self.current_map, &mut self.levels[self.level]] 
+            {
+                map.spawn = pos;
+                map.spawn_yaw = yaw;
+            }
+
+            self.main_player.actor.position = pos;
+            self.main_player.actor.yaw = yaw;
+
+            Ok(())
+        }
+
+        // Moves the player to pos, facing yaw. Err when pos is outside of the map, in a wall or on 
+        // an exit, the player stays where they were then
+        pub fn teleport(&mut self,
+                        pos: Vec2<f32>,
+                        yaw: f32) -> Result<(), String> 
+        {
+            let cell = self.current_map.cell_at(pos);
+
+            if self.current_map.is_solid(cell.x, cell.y) {
+                return Err(format!("Cannot teleport to {}, {}, it's in a wall or outside of the map", pos.x, pos.y));
+            }
+            if self.current_map.get(cell.x, cell.y) == Some(EXIT_CELL) {
+                return Err(format!("Cannot teleport to {}, {}, it's an exit", pos.x, pos.y));
+            }
+
+            self.main_player.actor.position = pos;
+            self.main_player.actor.yaw = normalize_angle(yaw);
+
+            Ok(())
+        }
+
+        // Back to where the current level starts, looking straight ahead
+        pub fn respawn(&mut self) 
+        {
+            self.main_player.actor.position = self.current_map.spawn;
+            self.main_player.actor.yaw = self.current_map.spawn_yaw;
+            self.camera.vertical_look = 0.;
+        }
+
+        pub fn level(&self) -> usize 
+        {
+            self.level
+        }
+
+        pub fn level_count(&self) -> usize 
+        {
+            self.levels.len()
+        }
+
+        /// In world units, steps move it by the held keys
+        ///
+        /// ```
+        /// use wolfenstein::game_logic::Game;
+        /// use wolfenstein::terminal::input::keys::{Key, KeyState};
+        ///
+        /// let mut game = Game::new();
+        /// let start = game.player_position();
+        ///
+        /// for _ in 0..4 {
+        ///     game.step(KeyState::default().with(Key::W), 0.05);
+        /// }
+        ///
+        /// let position = game.player_position();
+        /// println!("walked from {} to {}", start, position);
+        /// assert!(position.x != start.x || position.y != start.y);
+        /// ```
+        pub fn player_position(&self) -> Vec2<f32> 
+        {
+            self.main_player.actor.position
+        }
+
+        // Radians clockwise from north, from 0 to 2 PI
+        pub fn player_yaw(&self) -> f32 
+        {
+            self.main_player.actor.yaw
+        }
+
+        // How far the view is sheared up, as a fraction of the screen height, negative looks down
+        pub fn player_pitch(&self) -> f32 
+        {
+            self.camera.vertical_look
+        }
+
+        // Columns and rows of cells of the current level
+        pub fn map_size(&self) -> Vec2<i32> 
+        {
+            Vec2 { x: self.current_map.topography_x, y: self.current_map.topography_y }
+        }
+
+        // Width and height of a cell in world units
+        pub fn cell_dimensions(&self) -> Vec2<f32> 
+        {
+            Vec2 { x: self.current_map.cell_width, y: self.current_map.cell_height }
+        }
+
+        // What's on the screen as plain text, a header with its size and where the player stands 
+        // and a line per row of cells. For bug reports, so the colors are left out
+        pub fn dump_view(&self, 
+                         output: &Renderer) -> String 
+        {
+            let frame = output.snapshot();
+            let position = self.player_position();
+            let mut dump = String::new();
+
+            let _ = writeln!(dump, "# {}x{} cells", frame.width(), frame.height());
+            let _ = writeln!(dump, "# position {:.2}, {:.2} yaw {:.1} pitch {:.2} level {}", 
+                             position.x, 
+                             position.y, 
+                             self.player_yaw().to_degrees(), 
+                             self.player_pitch(), 
+                             self.level + 1);
+
+            if frame.width() > 0 {
+                for row in frame.data().chunks(frame.width() as usize) {
+                    dump.push_str(&String::from_utf8_lossy(row));
+                    dump.push('\n');
+                }
+            }
+
+            dump
+        }
+
+        // Screen y the walls are centered on
+        fn horizon(&self,
+                   screen_height: f32) -> f32 
+        {
+            // Screen units are half cells
+            screen_height * (0.5 + self.camera.vertical_look + self.view_bob_offset()) + self.shake_offset.y * 2.
+        }
+
+        // Jolts the view by up to intensity cells, which settles over intensity / SHAKE_SETTLE seconds. 
+        // A weaker shake doesn't cut a stronger one short
+        pub fn add_shake(&mut self,
+                         intensity: f32) 
+        {
+            if self.screen_shake {
+                self.shake = self.shake.max(intensity.clamp(0., MAX_SHAKE));
+            }
+        }
+
+        // Cells the view can still jitter by, 0 once the shake settled
+        pub fn shake(&self) -> f32 
+        {
+            self.shake
+        }
+
+        pub fn set_screen_shake(&mut self,
+                                screen_shake: bool) 
+        {
+            self.screen_shake = screen_shake;
+
+            if !screen_shake {
+                self.shake = 0.;
+                self.shake_offset = Vec2 { x: 0., y: 0. };
+            }
+        }
+
+        fn update_shake(&mut self,
+                        world_dt: f32) 
+        {
+            self.shake = (self.shake - SHAKE_SETTLE * world_dt).max(0.);
+
+            self.shake_offset = match self.shake > 0. {
+                true => Vec2 { x: self.rng.range(-self.shake, self.shake), y: self.rng.range(-self.shake, self.shake) },
+                false => Vec2 { x: 0., y: 0. },
+            };
+        }
+
+        // Only moves the picture, the player and the camera stay where they are
+        fn view_bob_offset(&self) -> f32 
+        {
+            if !self.view_bob {
+                return 0.;
+            }
+
+            let phase = self.weapon_bob * self.view_bob_frequency * std::f32::consts::TAU;
+
+            phase.sin() * self.view_bob_amplitude * self.view_bob_strength
+        }
+
+        fn move_player(&mut self,
+                       yaw: f32,
+                       world_dt: f32) 
+        {
+            let step = self.main_player.actor.movement_speed * world_dt;
+            if step <= 0. {
+                return;
+            }
+
+            let position = self.main_player.actor.position;
+            let direction = Vec2::from_angle(yaw);
+            let moved = Vec2 { 
+                x: position.x + direction.x * step,
+                y: position.y + direction.y * step,
+            };
+
+            if self.noclip 
+            {
+                // Through walls, but not out of the map
+                let map_size = Vec2 {
+                    x: self.current_map.topography_x as f32 * self.current_map.cell_width,
+                    y: self.current_map.topography_y as f32 * self.current_map.cell_height,
+                };
+
+                self.main_player.actor.position = moved.clamp(Vec2 { x: 0., y: 0. }, map_size);
+                return;
+            }
+
+            // A step into a wall keeps the part along it that's still open, so the player slides 
+            // along walls and stops in corners
+            let open = |pos: Vec2<f32>| {
+                let cell = self.current_map.cell_at(pos);
+                !self.current_map.is_solid(cell.x, cell.y)
+            };
+
+            self.main_player.actor.position = [moved, Vec2 { x: moved.x, y: position.y }, Vec2 { x: position.x, y: moved.y }]
+                .into_iter()
+                .find(|step| open(*step))
+                .unwrap_or(position);
+        }
+
+        // Where the view is cast from and which way it faces, shaken
+        fn eye(&self) -> (Vec2<f32>, f32) 
+        {
+            (self.camera.position, normalize_angle(self.camera.yaw + self.shake_yaw))
+        }
+
+        fn sync_camera(&mut self) 
+        {
+            if self.camera.follows_player {
+                self.camera.position = self.main_player.actor.position;
+                self.camera.yaw = self.main_player.actor.yaw;
+            }
+        }
+
+        // Detaches the camera from the player and puts it at position, facing yaw, for cutscenes 
+        // and security cameras. Anywhere goes, in walls or outside of the map
+        pub fn set_camera(&mut self,
+                          position: Vec2<f32>,
+                          yaw: f32) 
+        {
+            self.camera.position = position;
+            self.camera.yaw = normalize_angle(yaw);
+            self.camera.follows_player = false;
+        }
+
+        // Back to the player's eyes, stops spectating too
+        pub fn follow_player(&mut self) 
+        {
+            self.spectating = false;
+            self.camera.follows_player = true;
+            self.sync_camera();
+        }
+
+        pub fn camera_position(&self) -> Vec2<f32> 
+        {
+            self.camera.position
+        }
+
+        pub fn camera_yaw(&self) -> f32 
+        {
+            self.camera.yaw
+        }
+
+        // The walking and turning keys move the camera instead, the player is frozen 
+        // and the autopilot waits. Through walls, but not out of the map
+        fn fly_spectator(&mut self,
+                         input: KeyState,
+                         world_dt: f32) 
+        {
+            let bindings = self.bindings;
+            let speed = self.main_player.actor.movement_speed * world_dt;
+            let map_size = Vec2 {
+                x: self.current_map.topography_x as f32 * self.current_map.cell_width,
+                y: self.current_map.topography_y as f32 * self.current_map.cell_height,
+            };
+
+            for (action, turn) in [(Action::Forward, 0.), 
+                                   (Action::StrafeRight, HALF_PI), 
+                                   (Action::Back, PI), 
+                                   (Action::StrafeLeft, PI + HALF_PI)] 
+            {
+                if bindings.is_down(action, input) 
+                {
+                    let direction = Vec2::from_angle(self.camera.yaw + turn);
+                    let moved = Vec2 { 
+                        x: self.camera.position.x + direction.x * speed,
+                        y: self.camera.position.y + direction.y * speed,
+                    };
+
+                    self.camera.position = moved.clamp(Vec2 { x: 0., y: 0. }, map_size);
+                }
+            }
+
+            if bindings.is_down(Action::TurnRight, input) {
+                self.camera.yaw += self.main_player.actor.rotation_speed * world_dt;
+            }
+
+            if bindings.is_down(Action::TurnLeft, input) {
+                self.camera.yaw -= self.main_player.actor.rotation_speed * world_dt;
+            }
+
+            self.camera.yaw = normalize_angle(self.camera.yaw);
+
+            if bindings.is_down(Action::LookUp, input) {
+                self.camera.vertical_look += VERTICAL_LOOK_SPEED * world_dt;
+            }
+
+            if bindings.is_down(Action::LookDown, input) {
+                self.camera.vertical_look -= VERTICAL_LOOK_SPEED * world_dt;
+            }
+
+            self.camera.vertical_look = self.camera.vertical_look.clamp(-MAX_VERTICAL_LOOK, MAX_VERTICAL_LOOK);
+        }
+
+        // Spectating starts wherever the camera is, leaving it goes back to the player's eyes
+        pub fn set_spectating(&mut self,
+                              spectating: bool) 
+        {
+            if !spectating {
+                self.follow_player();
+                return;
+            }
+
+            self.spectating = true;
+            self.camera.follows_player = false;
+        }
+
+        pub fn is_spectating(&self) -> bool 
+        {
+            self.spectating
+        }
+
+        // The player seen from a camera that isn't at their eyes, a block of characters about half 
+        // as wide as it's tall, hidden behind walls where the depth buffer says so
+        fn draw_player_sprite(&self,
+                              output: &mut Renderer) 
+        {
+            // Of a wall's height, standing on the floor
+            const ACTOR_HEIGHT: f32 = 0.8;
+
+            if self.camera.follows_player {
+                return;
+            }
+
+            let (eye, eye_yaw) = self.eye();
+            let target = self.main_player.actor.position;
+            let Some((column, perpendicular)) = self.camera.project_point(eye, 
+                                                                          eye_yaw, 
+                                                                          target, 
+                                                                          self.depth_buffer.len() as i16) else {
+                return;
+            };
+
+            let screen_height = output.get_screen_dim().y as f32;
+            let (up, down) = wall_strip(perpendicular, 
+                                        self.projection_scale(output.get_screen_dim()), 
+                                        self.horizon(screen_height));
+            let up = down - (down - up) * ACTOR_HEIGHT;
+            // Cells are about twice as tall as wide and the strip is in half cells, so a quarter 
+            // of its height across is a figure about half as wide as tall
+            let half_width = ((down - up) / 8.).max(0.) as i32;
+            // The depth buffer holds the rays' lengths, not perpendicular distances
+            let distance = points_distance(eye, target);
+
+            output.set_color(DEFAULT_COLOR);
+            for x in column as i32 - half_width..=column as i32 + half_width 
+            {
+                match self.depth_buffer.get(x.max(0) as usize) 
+                {
+                    Some(depth) if x >= 0 && distance <= *depth => {
+                        output.draw_line(Vec2 { x: x as f32, y: up.max(0.) },
+                                         Vec2 { x: x as f32, y: down.min(screen_height - 1.) },
+                                         ACTOR_CHAR);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        fn calculate_and_draw(&mut self,
+                              output: &mut Renderer,
+                              mode: &ViewMode) 
+        {
+            if *mode == ViewMode::Mode2dAnd3d {
+                self.draw_split_view(output);
+                return;
+            }
+
+            let cells = output.get_cell_dim();
+
+            output.set_cell_aspect(self.camera.cell_aspect);
+            self.sync_camera();
+            self.last_frame_hits.clear();
+            // As wide as the view even when nothing gets cast, sprites drawn after look it up
+            self.depth_buffer.clear();
+            self.depth_buffer.resize(output.get_screen_dim().x.max(0) as usize, f32::INFINITY);
+
+            // Tiny terminals, or zero while the console is being resized
+            if cells.x < MIN_SCREEN_CELLS.x || cells.y < MIN_SCREEN_CELLS.y 
+            {
+                output.draw_string(Vec2 { 
+                                       x: ((cells.x as i32 - TOO_SMALL_MESSAGE.len() as i32) / 2).max(0),
+                                       y: cells.y as i32 / 2,
+                                   },
+                                   TOO_SMALL_MESSAGE);
+                return;
+            }
+
+            // Nothing to cast
+            if self.camera.fov < 1. {
+                return;
+            }
+
+            self.shake_yaw = self.shake_offset.x * self.camera.fov * RADIAN / cells.x as f32;
+
+            let (eye, eye_yaw) = self.eye();
+            let mut current_ray_pos: Vec2::<f32>;
+            let mut current_ray_angle = eye_yaw - (self.camera.fov / 2. * RADIAN);
+            
+            // Preallocate variables for calculations
+            let screen_width = output.get_screen_dim().x as f32;
+            // Below 1 when supersampling
+            let (columns_per_ray, ray_total) = match self.camera.ray_count {
+                0 => {
+                    let dx = self.camera.column_step.max(1) as f32;
+                    (dx, (screen_width / dx).ceil() as i32)
+                }
+                count => (screen_width / count as f32, count as i32),
+            };
+            let angle_step = self.camera.fov * RADIAN / screen_width * columns_per_ray;
+            let mut which_axis: Axis;
+            let mut ray_distance: f32;
+            let mut samples = ColumnSamples::default();
+            // Cell, axis and strip the previous ray hit, for finding where walls begin in Wireframe
+            let mut previous_hit: Option<(Vec2<i32>, Axis, f32, f32)> = None;
+
+            let view = self.map_view(output);
+
+            if *mode == ViewMode::Mode2d {
+                self.draw_map_2d(output, &view);
+                self.draw_range_ring(output, &view);
+            }
+
+            for ray in 0..ray_total 
+            {
+                // Stepped first, supersampled rays skip the rest of the loop
+                if ray > 0 {
+                    current_ray_angle += angle_step;
+                }
+
+                current_ray_angle = normalize_angle(current_ray_angle);
+                let ray_angle = current_ray_angle;
+
+                // Every ray starts where the previous one ended, so no column is skipped or drawn twice. 
+                // Supersampled rays get no columns at all, except the last one in every column
+                let ray_line = (ray as f32 * columns_per_ray).floor();
+                let next_line = match ray + 1 == ray_total {
+                    true => screen_width,
+                    false => ((ray + 1) as f32 * columns_per_ray).floor().min(screen_width),
+                };
+                let columns = next_line - ray_line;
+
+                (current_ray_pos, which_axis, _) = self.march_ray(eye, 
+                                                                  current_ray_angle);
+
+                if self.collect_hits {
+                    self.last_frame_hits.push(RayHit { 
+                        position: current_ray_pos, 
+                        cell: self.current_map.cell_at(current_ray_pos), 
+                        axis: which_axis,
+                    });
+                }
+            
+                match mode 
+                {
+                    ViewMode::Mode2d => {
+                        match which_axis {
+                            Axis::OnX => {
+                                output.draw_line(view.apply(eye),
+                                                 view.apply(current_ray_pos),
+                                                 self.palette.wall_x);
+                            }
+                            Axis::OnY => {
+                                output.draw_line(view.apply(eye),
+                                                 view.apply(current_ray_pos),
+                                                 self.palette.wall_y);
+                            }
+                        }
+                    }
+
+                    ViewMode::Mode3d => {
+                        ray_distance = points_distance(eye, current_ray_pos).ceil();
+                        // Perpendicular distance, so walls don't bulge towards the middle of the screen
+                        let perpendicular = ray_distance * (ray_angle - eye_yaw).cos();
+                        let brightness = self.wall_brightness(current_ray_pos, ray_distance, &which_axis);
+
+                        samples.add(ray_distance, perpendicular, brightness);
+                        if columns < 1. {
+                            continue;
+                        }
+
+                        let (ray_distance, perpendicular, brightness) = samples.take();
+                        self.write_depth(ray_line, columns, ray_distance);
+                        output.set_color(self.wall_color(current_ray_pos, brightness));
+
+                        let screen_height = output.get_screen_dim().y as f32;
+                        let (strip_up, strip_down) = wall_strip(perpendicular, 
+                                                                self.projection_scale(output.get_screen_dim()), 
+                                                                self.horizon(screen_height));
+                        let texture = self.current_map.texture(self.material_at(current_ray_pos));
+                        let texture_u = self.texture_u(current_ray_pos, &which_axis);
+
+                        // The same strip for all the columns of this ray
+                        for i in 0..columns as i32 
+                        {
+                            let up = Vec2 { 
+                                x: (ray_line + i as f32),
+                                y: strip_up,
+                            };
+
+                            let down = Vec2 { 
+                                x: (ray_line + i as f32),
+                                y: strip_down,
+                            };
+    
+                            if up.y > down.y {
+                                break;
+                            }
+
+                            // Textures are sampled from the unclamped strip
+                            if let Some(texture) = texture {
+                                draw_textured_strip(output, up, down, texture, texture_u);
+                                continue;
+                            }
+
+                            let up = Vec2 { x: up.x, y: up.y.max(0.) };
+                            let down = Vec2 { x: down.x, y: down.y.min(screen_height - 1.) };
+
+                            if self.lighting {
+                                output.draw_line(up, down, shade_char(brightness, self.gamma));
+                                continue;
+                            }
+
+                            match which_axis 
+                            {
+                                Axis::OnX => {
+                                    output.draw_line(up,
+                                                     down,
+                                                     self.palette.wall_x);
+                                }
+
+                                Axis::OnY => {
+                                    output.draw_line(up,
+                                                     down,
+                                                     self.palette.wall_y);
+                                }
+                            }
+                        }
+                    }
+
+                    ViewMode::Wireframe => {
+                        ray_distance = points_distance(eye, current_ray_pos).ceil();
+                        let perpendicular = ray_distance * (ray_angle - eye_yaw).cos();
+                        let brightness = self.wall_brightness(current_ray_pos, ray_distance, &which_axis);
+
+                        // Averaged like in Mode3d, the edges go by the last ray of the column
+                        samples.add(ray_distance, perpendicular, brightness);
+                        if columns < 1. {
+                            continue;
+                        }
+
+                        let (ray_distance, perpendicular, brightness) = samples.take();
+                        self.write_depth(ray_line, columns, ray_distance);
+                        output.set_color(self.wall_color(current_ray_pos, brightness));
+
+                        let screen_height = output.get_screen_dim().y as f32;
+                        let (strip_up, strip_down) = wall_strip(perpendicular, 
+                                                                self.projection_scale(output.get_screen_dim()), 
+                                                                self.horizon(screen_height));
+                        let cell = self.current_map.cell_at(current_ray_pos);
+                        let ch = match which_axis {
+                            Axis::OnX => self.palette.wall_x,
+                            Axis::OnY => self.palette.wall_y,
+                        };
+
+                        // A wall begins where the hit cell or face changes, 
+                        // its vertical edge spans both its strip and the previous one
+                        if let Some((previous_cell, previous_axis, previous_up, previous_down)) = previous_hit 
+                        {
+                            if previous_cell.x != cell.x || previous_cell.y != cell.y || previous_axis != which_axis 
+                            {
+                                output.draw_line(Vec2 { x: ray_line, y: strip_up.min(previous_up).max(0.) },
+                                                 Vec2 { x: ray_line, y: strip_down.max(previous_down).min(screen_height - 1.) },
+                                                 ch);
+                            }
+                        }
+
+                        previous_hit = Some((cell, which_axis, strip_up, strip_down));
+
+                        // Top and bottom edges only, for all the columns of this ray
+                        for i in 0..columns as i32 
+                        {
+                            let x = (ray_line + i as f32) as i32;
+
+                            output.draw_point(Vec2 { x, y: strip_up as i32 }, ch);
+                            output.draw_point(Vec2 { x, y: strip_down as i32 }, ch);
+                        }
+                    }
+
+                    // Drawn by draw_split_view()
+                    ViewMode::Mode2dAnd3d => {}
+                }
+
+            }
+
+            output.set_color(DEFAULT_COLOR);
+
+            // After all the rays, so none of them is drawn over a dot
+            if *mode == ViewMode::Mode2d {
+                for hit in &self.last_frame_hits {
+                    output.draw_dot(view.apply(hit.position), DEFAULT_DOT_RADIUS, false, HIT_CHAR);
+                }
+            }
+
+            self.draw_particles(output, mode, &view);
+
+            match mode 
+            {
+                ViewMode::Mode2d => {
+                    self.draw_fov_cone(output, &view);
+                    self.draw_player_2d(output, &view);
+                }
+                ViewMode::Mode3d | ViewMode::Wireframe => {
+                    self.draw_player_sprite(output);
+                    self.draw_crosshair(output);
+                }
+                ViewMode::Mode2dAnd3d => {}
+            }
+        }
+
+        // The map on the left half and the 3d view on the right one, each in a viewport of its own 
+        // and a column between them. The 3d view goes last, the depth buffer is for its columns
+        fn draw_split_view(&mut self,
+                           output: &mut Renderer) 
+        {
+            let cells = output.get_cell_dim();
+            let divider = cells.x as i32 / 2;
+
+            self.calculate_and_draw(&mut output.viewport(Vec2 { x: 0, y: 0 }, 
+                                                         Vec2 { x: divider, y: cells.y as i32 }), 
+                                    &ViewMode::Mode2d);
+            self.calculate_and_draw(&mut output.viewport(Vec2 { x: divider + 1, y: 0 }, 
+                                                         Vec2 { x: cells.x as i32 - divider - 1, y: cells.y as i32 }), 
+                                    &ViewMode::Mode3d);
+
+            output.set_color(DEFAULT_COLOR);
+            for y in 0..cells.y as i32 {
+                output.draw_point_unnormalized(Vec2 { x: divider, y }, DIVIDER_CHAR);
+            }
+        }
+
+        // Last thing drawn in Mode3d, so it's on top of the walls and particles
+        fn draw_crosshair(&self,
+                          output: &mut Renderer) 
+        {
+            if let Some(ch) = self.crosshair {
+                let cells = output.get_cell_dim();
+                output.draw_point_unnormalized(Vec2 { x: cells.x as i32 / 2, y: cells.y as i32 / 2 }, ch);
+            }
+        }
+
+        // None hides the crosshair
+        pub fn set_crosshair(&mut self,
+                             crosshair: Option<u8>) 
+        {
+            self.crosshair = crosshair;
+        }
+
+        // None hides the prompt, range is in cells
+        pub fn set_interact_prompt(&mut self,
+                                   prompt: Option<String>,
+                                   range: f32) 
+        {
+            self.interact_prompt = prompt;
+            self.interact_range = range.clamp(0., MAX_INTERACT_RANGE);
+        }
+
+        // The exit cell straight ahead of the player, no further than interact_range cells and 
+        // before the wall the shot would hit. Exits only count while there's a level after this one
+        pub fn interact_target(&self) -> Option<Vec2<i32>> 
+        {
+            // Samples per cell along the ray, enough not to step over the corner of a cell
+            const SAMPLES_PER_CELL: f32 = 8.;
+
+            if self.level + 1 >= self.levels.len() {
+                return None;
+            }
+
+            let origin = self.main_player.actor.position;
+            let (wall, _, hit) = self.march_ray(origin, self.main_player.actor.yaw);
+            let cell_size = self.current_map.cell_size();
+            let mut range = self.interact_range * cell_size;
+
+            if hit {
+                range = range.min(points_distance(origin, wall));
+            }
+
+            let direction = Vec2::from_angle(self.main_player.actor.yaw);
+            let step = self.current_map.cell_width.min(self.current_map.cell_height) / SAMPLES_PER_CELL;
+            let mut traveled = 0.;
+
+            while traveled <= range 
+            {
+                let point = Vec2 { x: origin.x + direction.x * traveled, y: origin.y + direction.y * traveled };
+                let cell = self.calculate_current_square(point);
+
+                if self.current_map.get(cell.x, cell.y) == Some(EXIT_CELL) {
+                    return Some(cell);
+                }
+
+                traveled += step;
+            }
+
+            None
+        }
+
+        pub fn set_allow_noclip(&mut self,
+                                allow: bool) 
+        {
+            self.allow_noclip = allow;
+            self.noclip &= allow;
+        }
+
+        pub fn set_view_bob(&mut self,
+                            bob: bool,
+                            amplitude: f32,
+                            frequency: f32) 
+        {
+            self.view_bob = bob;
+            self.view_bob_amplitude = amplitude.clamp(0., MAX_VIEW_BOB_AMPLITUDE);
+            self.view_bob_frequency = frequency.clamp(0., MAX_VIEW_BOB_FREQUENCY);
+        }
+
+        pub fn set_compass(&mut self,
+                           show: bool,
+                           position: Vec2<i32>) 
+        {
+            self.show_compass = show;
+            self.compass_position = position;
+        }
+
+        // Height of a terminal cell divided by its width, for fonts that aren't the usual 2:1
+        pub fn set_cell_aspect(&mut self,
+                               aspect: f32) 
+        {
+            // The projection divides by it
+            self.camera.cell_aspect = aspect.max(f32::EPSILON);
+        }
+
+        // Degrees, 0 goes back to deriving it from the fov, see Camera
+        pub fn set_vertical_fov(&mut self,
+                                degrees: f32) 
+        {
+            self.camera.vertical_fov = degrees.clamp(0., MAX_VERTICAL_FOV);
+        }
+
+        // Columns per cast ray, 1 is full resolution, see Camera
+        pub fn set_column_step(&mut self,
+                               step: u8) 
+        {
+            self.camera.column_step = step.clamp(1, MAX_COLUMN_STEP);
+        }
+
+        // Rays over the whole fov, 0 casts one for every column_step columns, see Camera
+        pub fn set_ray_count(&mut self,
+                             count: u32) 
+        {
+            self.camera.ray_count = count.min(MAX_RAY_COUNT);
+        }
+
+        pub fn set_show_fov_cone(&mut self,
+                                 show: bool) 
+        {
+            self.show_fov_cone = show;
+        }
+
+        pub fn set_collect_hits(&mut self,
+                                collect: bool) 
+        {
+            self.collect_hits = collect;
+            self.last_frame_hits.clear();
+        }
+
+        // Empty unless set_collect_hits(true), in the order the rays were cast, left to right
+        pub fn last_frame_hits(&self) -> &[RayHit] 
+        {
+            &self.last_frame_hits
+        }
+
+        pub fn set_fog_of_war(&mut self,
+                              fog: bool) 
+        {
+            self.fog_of_war = fog;
+        }
+
+        // False outside of the map, and for cells of the current level no ray reached yet
+        pub fn is_explored(&self,
+                           cell: Vec2<i32>) -> bool 
+        {
+            let explored = match self.explored.get(self.level) {
+                Some(explored) => explored,
+                None => return false,
+            };
+
+            self.current_map.index(cell.x, cell.y)
+                .and_then(|i| explored.get(i).copied())
+                .unwrap_or(false)
+        }
+
+        // Marks what the player sees, rays across their field of view, wherever the camera is
+        fn explore(&mut self) 
+        {
+            // Rays per degree of the field of view, a few cells away two of them are less than a cell apart
+            const RAYS_PER_DEGREE: f32 = 2.;
+
+            let origin = self.main_player.actor.position;
+            let rays = (self.camera.fov * RAYS_PER_DEGREE).ceil().max(1.) as i32;
+            let first = self.main_player.actor.yaw - self.camera.fov / 2. * RADIAN;
+            let step = self.camera.fov * RADIAN / rays as f32;
+
+            for ray in 0..=rays 
+            {
+                let (hit_pos, _, _) = self.march_ray(origin, normalize_angle(first + step * ray as f32));
+                self.reveal_ray(origin, hit_pos);
+            }
+        }
+
+        // Marks every cell from origin to where a ray stopped, the one it stopped in too
+        fn reveal_ray(&mut self,
+                      origin: Vec2<f32>,
+                      hit_pos: Vec2<f32>) 
+        {
+            // Half a cell, so a ray can't jump over a cell it crosses more than a corner of
+            let step = self.current_map.cell_width.min(self.current_map.cell_height) / 2.;
+            let length = points_distance(origin, hit_pos);
+            let samples = (length / step).ceil() as i32;
+
+            let cells = self.current_map.topography.len();
+            if self.explored.len() < self.levels.len() {
+                self.explored.resize(self.levels.len(), Vec::new());
+            }
+            let explored = &mut self.explored[self.level];
+            if explored.len() != cells {
+                *explored = vec![false; cells];
+            }
+
+            for sample in 0..=samples 
+            {
+                let pos = origin.lerp(hit_pos, sample as f32 / samples.max(1) as f32);
+                let cell = self.current_map.cell_at(pos);
+
+                if let Some(i) = self.current_map.index(cell.x, cell.y) {
+                    explored[i] = true;
+                }
+            }
+        }
+
+        pub fn set_show_fps(&mut self,
+                            show: bool) 
+        {
+            self.show_fps = show;
+        }
+
+        pub fn map_zoom(&self) -> f32 
+        {
+            self.map_zoom
+        }
+
+        pub fn set_map_zoom(&mut self,
+                            zoom: f32) 
+        {
+            if zoom > 0. {
+                self.map_zoom = zoom;
+            }
+        }
+
+        fn map_view(&self,
+                    output: &Renderer) -> MapView 
+        {
+            let screen = output.get_screen_dim();
+            let map_width = self.current_map.topography_x as f32 * self.current_map.cell_width;
+            let map_height = self.current_map.topography_y as f32 * self.current_map.cell_height;
+            let fit_scale = (screen.x as f32 / map_width).min(screen.y as f32 / map_height);
+
+            MapView {
+                scale: fit_scale * self.map_zoom,
+                focus: self.eye().0,
+                screen_center: Vec2 { x: screen.x as f32 / 2., y: screen.y as f32 / 2. },
+            }
+        }
+
+        fn draw_map_2d(&self,
+                       output: &mut Renderer,
+                       view: &MapView) 
+        {
+            for y in 0..self.current_map.topography_y 
+            {
+                for x in 0..self.current_map.topography_x 
+                {
+                    if self.fog_of_war && !self.is_explored(Vec2 { x, y }) {
+                        continue;
+                    }
+
+                    if let CellShape::Pillar(radius) = self.current_map.shape(x, y) 
+                    {
+                        let cell = self.current_map.cell_width.min(self.current_map.cell_height);
+                        output.draw_ring(view.apply(self.current_map.cell_center(Vec2 { x, y })), 
+                                         radius * cell * view.scale, 
+                                         WALL_2D_CHAR);
+                        continue;
+                    }
+
+                    let ch = match self.current_map.get(x, y) {
+                        Some(EXIT_CELL) => EXIT_2D_CHAR,
+                        _ if self.current_map.is_solid(x, y) => WALL_2D_CHAR,
+                        _ => continue,
+                    };
+
+                    let (top_left, bottom_right) = self.current_map.cell_bounds(Vec2 { x, y });
+
+                    output.fill_rect(view.apply(top_left), view.apply(bottom_right), ch);
+                }
+            }
+        }
+
+        // How far the camera sees
+        fn draw_range_ring(&self,
+                           output: &mut Renderer,
+                           view: &MapView) 
+        {
+            let radius = self.max_view_distance();
+
+            output.draw_ring(view.apply(self.main_player.actor.position), radius * view.scale, RING_CHAR);
+        }
+
+        // Over the rays, so the fov's edges show even where the rays stop short at a wall
+        fn draw_fov_cone(&self,
+                         output: &mut Renderer,
+                         view: &MapView) 
+        {
+            if !self.show_fov_cone {
+                return;
+            }
+
+            let (position, yaw) = self.eye();
+            let half_fov = self.camera.fov / 2. * RADIAN;
+            let length = self.max_view_distance();
+
+            for edge in [yaw - half_fov, yaw + half_fov] 
+            {
+                let dir = Vec2::from_angle(edge);
+                let end = Vec2 { x: position.x + dir.x * length, y: position.y + dir.y * length };
+
+                output.draw_line(view.apply(position), view.apply(end), CONE_CHAR);
+            }
+        }
+
+        // On top of the rays, so it's visible where the player is and where they look
+        fn draw_player_2d(&self,
+                          output: &mut Renderer,
+                          view: &MapView) 
+        {
+            const FACING_LENGTH: f32 = 1.5;
+            // Faint to solid, the antialiased ray shows where inside a cell the player is
+            const FACING_RAMP: &[u8] = &[b'.', b':', FACING_CHAR];
+
+            let position = self.main_player.actor.position;
+            let dir = Vec2::from_angle(self.main_player.actor.yaw);
+            let length = FACING_LENGTH * self.current_map.cell_size();
+            let tip = Vec2 { x: position.x + dir.x * length, y: position.y + dir.y * length };
+
+            output.draw_line_aa(view.apply(position), view.apply(tip), FACING_RAMP);
+
+            // Rounded rather than truncated, so the marker doesn't lag behind when moving left or up
+            let marker = view.apply(position);
+            output.draw_point(Vec2 { x: marker.x.round() as i32, y: marker.y.round() as i32 }, PLAYER_2D_CHAR);
+        }
+
+        // Camera::boundary_epsilon in world units
+        fn boundary_epsilon(&self) -> f32 
+        {
+            self.camera.boundary_epsilon * self.current_map.cell_size()
+        }
+
+        // Fraction of the cell size, see Camera
+        pub fn set_boundary_epsilon(&mut self,
+                                    epsilon: f32) 
+        {
+            self.camera.boundary_epsilon = epsilon.clamp(0., MAX_BOUNDARY_EPSILON);
+        }
+
+        fn max_view_distance(&self) -> f32 
+        {
+            self.camera.max_visible_distance as f32 * self.current_map.cell_size()
+        }
+
+        // Marches square by square from origin until a wall is hit or one of the camera's limits
+        // is reached, returns where the ray ended, on which axis and if it hit anything
+        pub fn march_ray(&self,
+                         origin: Vec2<f32>,
+                         angle: f32) -> (Vec2<f32>, Axis, bool) 
+        {
+            // Keeps jumps off the last open cell's far border, where rounding could land them in the wall
+            const SKIP_MARGIN: f32 = 0.01;
+
+            let max_distance = self.max_view_distance();
+            let min_side = self.current_map.cell_width.min(self.current_map.cell_height);
+            let direction = Vec2::from_angle(angle);
+            let mut current_ray_pos = origin;
+            let mut which_axis = Axis::OnX;
+            let mut steps = 0;
+
+            while steps < self.camera.max_steps 
+            {
+                let traveled = points_distance(origin, current_ray_pos);
+                if traveled > max_distance {
+                    break;
+                }
+
+                // Check in which square we are
+                let mut current_square = self.calculate_current_square(current_ray_pos);
+
+                // Leaving the map counts as a hit too, there's nothing past it to march through
+                if self.current_map.is_solid(current_square.x, current_square.y) 
+                {
+                    match self.current_map.shape(current_square.x, current_square.y) {
+                        CellShape::Pillar(radius) => {
+                            if let Some(hit) = self.hit_pillar(origin, direction, current_square, radius) {
+                                return (hit.0, hit.1, true);
+                            }
+                        }
+                        _ => return (current_ray_pos, which_axis, true),
+                    }
+                }
+
+                // Straight over the open cells around this one. The jump stops short of the visible 
+                // distance and counts every border it crosses as a step, so the ray still ends 
+                // where crossing the cells one by one would have ended it
+                let clearance = self.current_map.open_distance(current_square.x, current_square.y) - 1;
+                let skip = ((clearance as f32 - SKIP_MARGIN) * min_side)
+                    .min(max_distance - traveled - SKIP_MARGIN * min_side);
+
+                if clearance > 0 && skip > 0. 
+                {
+                    let landing = Vec2 { 
+                        x: current_ray_pos.x + direction.x * skip,
+                        y: current_ray_pos.y + direction.y * skip,
+                    };
+                    let landing_square = self.calculate_current_square(landing);
+                    let crossed = (landing_square.x - current_square.x).abs() + (landing_square.y - current_square.y).abs();
+
+                    if steps + crossed < self.camera.max_steps 
+                    {
+                        current_ray_pos = landing;
+                        current_square = landing_square;
+                        steps += crossed;
+                    }
+                }
+
+                let current_top_left_of_square = self.current_map.cell_top_left(current_square);
+                
+                let hit = cast_ray(&current_ray_pos,
+                                   &angle,
+                                   &current_top_left_of_square,
+                                   &self.current_map.cell_width,
+                                   &self.current_map.cell_height,
+                                   self.boundary_epsilon());
+
+                current_ray_pos = hit.0;
+                which_axis = hit.1;
+                steps += 1;
+
+                // Pushed over both borders, past the two cells beside the corner. A wall in either 
+                // of them closes the gap, the ray would leak between them otherwise
+                if hit.2 
+                {
+                    if let Some(corner) = self.corner_hit(current_square, current_ray_pos, direction) {
+                        return (corner.0, corner.1, true);
+                    }
+                }
+            }
+
+            (current_ray_pos, which_axis, false)
+        }
+
+        // Which of the cells beside the corner square's ray went through stops it, pushed is where 
+        // cast_ray() put it. Both are as near, the one across the y border goes first so a corner 
+        // between two walls is always hit on Axis::OnY. Pillars don't reach into their corners
+        fn corner_hit(&self,
+                      square: Vec2<i32>,
+                      pushed: Vec2<f32>,
+                      direction: Vec2<f32>) -> Option<(Vec2<f32>, Axis)> 
+        {
+            let step = Vec2 { 
+                x: if direction.x > 0. { 1 } else { -1 },
+                y: if direction.y > 0. { 1 } else { -1 },
+            };
+            let is_wall = |x: i32, y: i32| matches!(self.current_map.shape(x, y), CellShape::Wall);
+            // Pulled back over the other border, into the wall's cell
+            let back = 2. * self.boundary_epsilon();
+
+            if is_wall(square.x, square.y + step.y) {
+                return Some((Vec2 { x: pushed.x - step.x as f32 * back, y: pushed.y }, Axis::OnY));
+            }
+
+            if is_wall(square.x + step.x, square.y) {
+                return Some((Vec2 { x: pushed.x, y: pushed.y - step.y as f32 * back }, Axis::OnX));
+            }
+
+            None
+        }
+
+        // The same ray as march_ray() walked with a DDA, the ray lengths to the next vertical and 
+        // horizontal border are kept and the nearer border is crossed. No tan() and nothing pushed 
+        // over borders, so nothing for rounding to get stuck on. Only the tests and benches use it so far
+        pub fn march_ray_dda(&self,
+                             origin: Vec2<f32>,
+                             angle: f32) -> (Vec2<f32>, Axis, bool) 
+        {
+            let max_distance = self.max_view_distance();
+            let cell_width = self.current_map.cell_width;
+            let cell_height = self.current_map.cell_height;
+            let direction = Vec2::from_angle(angle);
+            let step = Vec2 { 
+                x: if direction.x > 0. { 1 } else { -1 },
+                y: if direction.y > 0. { 1 } else { -1 },
+            };
+            let mut cell = self.calculate_current_square(origin);
+
+            // Ray length across a whole cell and to the first border, on each axis. 
+            // A ray along the other axis never gets there
+            let length = |border: f32, from: f32, direction: f32| match direction {
+                0. => f32::INFINITY,
+                d => ((border - from) / d).abs(),
+            };
+            let across = Vec2 { x: length(cell_width, 0., direction.x), y: length(cell_height, 0., direction.y) };
+            let first_border = self.current_map.cell_top_left(Vec2 { x: cell.x + step.x.max(0), y: cell.y + step.y.max(0) });
+            let mut next = Vec2 { 
+                x: length(first_border.x, origin.x, direction.x), 
+                y: length(first_border.y, origin.y, direction.y),
+            };
+
+            let mut distance = 0.;
+            let mut which_axis = Axis::OnX;
+            let mut steps = 0;
+
+            while steps < self.camera.max_steps && distance <= max_distance 
+            {
+                if self.current_map.is_solid(cell.x, cell.y) 
+                {
+                    match self.current_map.shape(cell.x, cell.y) {
+                        CellShape::Pillar(radius) => {
+                            if let Some(hit) = self.hit_pillar(origin, direction, cell, radius) {
+                                return (hit.0, hit.1, true);
+                            }
+                        }
+                        _ => {
+                            let hit = Vec2 { x: origin.x + direction.x * distance, y: origin.y + direction.y * distance };
+                            return (hit, which_axis, true);
+                        }
+                    }
+                }
+
+                if next.x < next.y 
+                {
+                    distance = next.x;
+                    next.x += across.x;
+                    cell.x += step.x;
+                    which_axis = Axis::OnX;
+                }
+                else 
+                {
+                    distance = next.y;
+                    next.y += across.y;
+                    cell.y += step.y;
+                    which_axis = Axis::OnY;
+                }
+
+                steps += 1;
+            }
+
+            (Vec2 { x: origin.x + direction.x * distance, y: origin.y + direction.y * distance }, which_axis, false)
+        }
+
+        // Rays from a few places in every open cell of the current level, angles of them spread over 
+        // a full turn, for checking and timing the ray casters against each other
+        pub fn caster_rays(&self,
+                           angles: u32) -> Vec<(Vec2<f32>, f32)> 
+        {
+            // Where in a cell rays start from, as fractions of its size
+            const ORIGINS: [(f32, f32); 5] = [(0.5, 0.5), (0.2, 0.3), (0.8, 0.25), (0.3, 0.75), (0.7, 0.7)];
+
+            let mut rays = Vec::new();
+            for y in 0..self.current_map.topography_y 
+            {
+                for x in 0..self.current_map.topography_x 
+                {
+                    if self.current_map.is_solid(x, y) {
+                        continue;
+                    }
+
+                    let top_left = self.current_map.cell_top_left(Vec2 { x, y });
+                    for (fx, fy) in ORIGINS 
+                    {
+                        let origin = Vec2 { 
+                            x: top_left.x + fx * self.current_map.cell_width, 
+                            y: top_left.y + fy * self.current_map.cell_height,
+                        };
+
+                        for i in 0..angles {
+                            rays.push((origin, i as f32 / angles as f32 * TWO_PI));
+                        }
+                    }
+                }
+            }
+
+            rays
+        }
+
+        // Where the ray from origin enters the pillar in cell, with the axis its surface faces closer to.
+        // None when the ray passes it by, then the ray crosses the cell like an open one. Costs a square
+        // root for every ray reaching a pillar's cell, and those cells are never skipped over, so rays 
+        // next to pillars march cell by cell just like next to walls
+        fn hit_pillar(&self,
+                      origin: Vec2<f32>,
+                      direction: Vec2<f32>,
+                      cell: Vec2<i32>,
+                      radius: f32) -> Option<(Vec2<f32>, Axis)> 
+        {
+            let center = self.current_map.cell_center(cell);
+            let radius = radius * self.current_map.cell_width.min(self.current_map.cell_height);
+            let offset = Vec2 { x: origin.x - center.x, y: origin.y - center.y };
+
+            // The camera is inside of it, like standing in a wall with noclip
+            let outside = offset.dot(offset) - radius * radius;
+            if outside <= 0. {
+                return Some((origin, Axis::OnX));
+            }
+
+            // Closer of the two crossings of the circle, direction is a unit vector
+            let along = offset.dot(direction);
+            let discriminant = along * along - outside;
+            if discriminant < 0. {
+                return None;
+            }
+
+            let distance = -along - discriminant.sqrt();
+            if distance < 0. {
+                return None;
+            }
+
+            let hit = Vec2 { x: origin.x + direction.x * distance, y: origin.y + direction.y * distance };
+            let axis = match (hit.x - center.x).abs() >= (hit.y - center.y).abs() {
+                true => Axis::OnX,
+                false => Axis::OnY,
+            };
+
+            Some((hit, axis))
+        }
+
+        // Sets what fills a cell of the current level, see CellShape. Err outside of the map
+        pub fn set_cell_shape(&mut self,
+                              cell: Vec2<i32>,
+                              shape: CellShape) -> Result<(), String> 
+        {
+            self.current_map.set_shape(cell.x, cell.y, shape)?;
+            self.levels[self.level].set_shape(cell.x, cell.y, shape)
+        }
+
+        // Everything outside of the map counts as a plain wall
+        fn material_at(&self,
+                       pos: Vec2<f32>) -> i32 
+        {
+            let square = self.calculate_current_square(pos);
+
+            self.current_map.get(square.x, square.y).unwrap_or(1)
+        }
+
+        // Where along the wall the ray hit, from 0 to 1 across a single cell
+        fn texture_u(&self,
+                     hit_pos: Vec2<f32>,
+                     axis: &Axis) -> f32 
+        {
+            // Around a pillar, once per side of its cell
+            if let Some(normal) = self.current_map.pillar_normal(hit_pos) {
+                let along = normal.angle() / std::f32::consts::TAU * 4.;
+
+                return along - along.floor();
+            }
+
+            let along = match axis {
+                Axis::OnX => hit_pos.y / self.current_map.cell_height,
+                Axis::OnY => hit_pos.x / self.current_map.cell_width,
+            };
+
+            along - along.floor()
+        }
+
+        // Every level's material, so it stays when the next level loads
+        pub fn set_texture(&mut self,
+                           material: i32,
+                           texture: Texture) 
+        {
+            for map in self.levels.iter_mut() 
+            {
+                map.textures.insert(material, texture.clone());
+            }
+
+            self.current_map.textures.insert(material, texture);
+        }
+
+        // Cycles material through frames on the tick timer, ticks_per_frame ticks each, 
+        // so the speed is the same at any frame rate. Err without frames or with 0 ticks per frame
+        pub fn set_animated_texture(&mut self,
+                                    material: i32,
+                                    frames: Vec<Texture>,
+                                    ticks_per_frame: u32) -> Result<(), String> 
+        {
+            if frames.is_empty() {
+                return Err(format!("Animation of material {} has no frames", material));
+            }
+
+            if ticks_per_frame == 0 {
+                return Err(format!("Animation of material {} needs at least 1 tick per frame", material));
+            }
+
+            let animation = Animation { frames, ticks_per_frame, frame: 0, ticks: 0 };
+
+            for map in self.levels.iter_mut() 
+            {
+                map.animations.insert(material, animation.clone());
+            }
+
+            self.current_map.animations.insert(material, animation);
+            Ok(())
+        }
+
+        // Frame the material's animation is on, None when it isn't animated
+        pub fn texture_frame(&self,
+                             material: i32) -> Option<usize> 
+        {
+            self.current_map.animations.get(&material).map(|animation| animation.frame)
+        }
+
+        pub fn set_lighting(&mut self,
+                            lighting: bool) 
+        {
+            self.lighting = lighting;
+        }
+
+        // 1 lights both sides the same, 0 makes the y sides black
+        pub fn set_side_shade(&mut self,
+                              shade: f32) 
+        {
+            self.side_shade = shade.clamp(0., 1.);
+        }
+
+        pub fn set_distance_fog(&mut self,
+                                fog: bool) 
+        {
+            self.distance_fog = fog;
+        }
+
+        // The wall characters, side shade and distance fog of the preset, and the renderer's 
+        // share of it
+        pub fn apply_preset(&mut self,
+                            output: &mut Renderer,
+                            preset: RenderPreset) 
+        {
+            let (wall_x, wall_y) = preset.wall_chars();
+
+            self.palette = Palette { wall_x, wall_y };
+            self.set_side_shade(preset.side_shade());
+            self.set_distance_fog(preset.distance_fog());
+            output.apply_preset(preset);
+        }
+
+        // The config's preset, then the look settings that came after it in the file
+        pub fn apply_look(&mut self,
+                          output: &mut Renderer,
+                          config: &Config) 
+        {
+            self.apply_preset(output, config.preset);
+
+            if let Some(palette) = config.palette {
+                self.palette = palette;
+            }
+            if let Some(shade) = config.side_shade {
+                self.set_side_shade(shade);
+            }
+            if let Some(fog) = config.distance_fog {
+                self.set_distance_fog(fog);
+            }
+            if let Some(bold) = config.bold_colors {
+                output.set_bold_colors(bold);
+            }
+        }
+
+        pub fn set_gamma(&mut self,
+                         gamma: f32) 
+        {
+            self.gamma = gamma.clamp(MIN_GAMMA, MAX_GAMMA);
+        }
+
+        // Runs a tick for every tick_duration of world time that passed, several after a slow frame
+        fn advance_ticks(&mut self,
+                         world_dt: f32) 
+        {
+            // Rounded to whole microseconds, so frame times that add up to a tick on paper 
+            // don't come up short of it in floats
+            self.tick_elapsed += Duration::from_micros((world_dt.max(0.) * 1e6).round() as u64);
+
+            while self.tick_elapsed >= self.tick_duration 
+            {
+                self.tick_elapsed -= self.tick_duration;
+
+                if self.ticks_this_frame == MAX_TICKS_PER_FRAME {
+                    continue;
+                }
+
+                self.ticks_this_frame += 1;
+                self.tick();
+            }
+        }
+
+        // One fixed step of everything that doesn't move smoothly with the frame time
+        fn tick(&mut self) 
+        {
+            self.tick_count += 1;
+
+            for animation in self.current_map.animations.values_mut() {
+                animation.tick();
+            }
+        }
+
+        // Ticks the last step() ran, 0 while paused
+        pub fn ticks_this_frame(&self) -> u32 
+        {
+            self.ticks_this_frame
+        }
+
+        // Ticks since the game started, over every level
+        pub fn tick_count(&self) -> u64 
+        {
+            self.tick_count
+        }
+
+        pub fn set_tick_duration(&mut self,
+                                 duration: Duration) 
+        {
+            self.tick_duration = duration.clamp(MIN_TICK_DURATION, MAX_TICK_DURATION);
+        }
+
+        // How long a key has to be up before pressing it again toggles again
+        pub fn set_debounce(&mut self,
+                            window: Duration) 
+        {
+            self.debounce = Debounce::new(window);
+        }
+
+        pub fn set_time_scale(&mut self,
+                              time_scale: f32) 
+        {
+            self.time_scale = time_scale.clamp(0., MAX_TIME_SCALE);
+        }
+
+        // Cells outside of the map are ignored
+        pub fn set_light(&mut self,
+                         x: i32,
+                         y: i32,
+                         light: f32) 
+        {
+            if let Some(i) = self.current_map.index(x, y) {
+                self.current_map.lights[i] = light.clamp(0., 1.);
+            }
+        }
+
+        // Everything outside of the map is fully lit
+        fn light_at(&self,
+                    pos: Vec2<f32>) -> f32 
+        {
+            let square = self.calculate_current_square(pos);
+
+            self.current_map.light(square.x, square.y).unwrap_or(1.)
+        }
+
+        // Fades out with the distance and is darker for walls hit on the y axis, 
+        // with lighting on it's also multiplied by the light of the cell that was hit
+        fn wall_brightness(&self,
+                           hit_pos: Vec2<f32>,
+                           distance: f32,
+                           axis: &Axis) -> f32 
+        {
+            const MIN_BRIGHTNESS: f32 = 0.15;
+
+            let max_distance = self.max_view_distance();
+            let fog = match self.distance_fog {
+                true => (1. - distance / max_distance).clamp(MIN_BRIGHTNESS, 1.),
+                false => 1.,
+            };
+
+            let side = match (self.current_map.pillar_normal(hit_pos), axis) {
+                // Pillars turn smoothly from the x sides' shade to the y sides' one
+                (Some(normal), _) => self.side_shade + (1. - self.side_shade) * normal.x.abs(),
+                (None, Axis::OnX) => 1.,
+                (None, Axis::OnY) => self.side_shade,
+            };
+
+            if !self.lighting {
+                return fog * side;
+            }
+
+            fog * self.light_at(hit_pos) * side
+        }
+
+        // Tint of the material the ray ended in
+        fn wall_color(&self,
+                      hit_pos: Vec2<f32>,
+                      brightness: f32) -> Color 
+        {
+            material_color(self.material_at(hit_pos)).scale(brightness)
+        }
+
+        fn write_depth(&mut self,
+                       ray_line: f32,
+                       dx: f32,
+                       distance: f32) 
+        {
+            for i in 0..dx as i32 
+            {
+                if let Some(depth) = self.depth_buffer.get_mut((ray_line + i as f32) as usize) {
+                    *depth = distance;
+                }
+            }
+        }
+
+        // Shoots straight ahead, sparks fly out of the wall if anything was hit
+        pub fn fire(&mut self) -> Option<Vec2<f32>> 
+        {
+            let yaw = self.main_player.actor.yaw;
+            let (hit_pos, axis, hit) = self.march_ray(self.main_player.actor.position, yaw);
+
+            if !hit {
+                return None;
+            }
+
+            self.weapon_flash = WEAPON_FLASH_TIME;
+            self.add_shake(FIRE_SHAKE);
+
+            // Sparks bounce off the wall the way the shot came in
+            let bounce = Vec2::from_angle(yaw).reflect(axis.normal());
+            self.spawn_burst(hit_pos, bounce.x.atan2(-bounce.y));
+            Some(hit_pos)
+        }
+
+        fn spawn_burst(&mut self,
+                       pos: Vec2<f32>,
+                       angle: f32) 
+        {
+            for _ in 0..PARTICLE_BURST 
+            {
+                if self.particles.len() >= MAX_PARTICLES {
+                    self.particles.remove(0);
+                }
+
+                let spread = self.rng.range(-PARTICLE_SPREAD / 2., PARTICLE_SPREAD / 2.);
+                let speed = self.rng.range(0.5, 1.) * PARTICLE_SPEED;
+                let dir = Vec2::from_angle(angle + spread);
+
+                self.particles.push(Particle {
+                    pos,
+                    vel: Vec2 { x: dir.x * speed, y: dir.y * speed },
+                    life: PARTICLE_LIFE,
+                    ch: SPARK_CHAR,
+                });
+            }
+        }
+
+        fn update_particles(&mut self,
+                            dt: f32) 
+        {
+            for p in self.particles.iter_mut() 
+            {
+                p.pos.x += p.vel.x * dt;
+                p.pos.y += p.vel.y * dt;
+                p.life -= dt;
+            }
+
+            self.particles.retain(|p| p.life > 0.);
+        }
+
+        fn draw_particles(&self,
+                          output: &mut Renderer,
+                          mode: &ViewMode,
+                          view: &MapView) 
+        {
+            let (position, yaw) = self.eye();
+            let screen_height = output.get_screen_dim().y as f32;
+            let scale = self.projection_scale(output.get_screen_dim());
+
+            for p in self.particles.iter() 
+            {
+                if let ViewMode::Mode2d = mode {
+                    let screen_pos = view.apply(p.pos);
+                    output.draw_point(Vec2 { x: screen_pos.x as i32, y: screen_pos.y as i32 }, p.ch);
+                    continue;
+                }
+
+                let column = match self.camera.project_point(position, 
+                                                             yaw, 
+                                                             p.pos, 
+                                                             self.depth_buffer.len() as i16) {
+                    Some((column, _)) => column as usize,
+                    None => continue,
+                };
+
+                // The depth buffer holds the rays' lengths, not perpendicular distances
+                let distance = points_distance(position, p.pos);
+
+                match self.depth_buffer.get(column) 
+                {
+                    Some(depth) if distance <= *depth => {
+                        let (up, down) = wall_strip(distance, scale, self.horizon(screen_height));
+                        output.draw_point(Vec2 { x: column as i32, y: ((up + down) / 2.) as i32 }, p.ch);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        #[inline]
+        fn calculate_current_square(&self,
+                                    pos: Vec2<f32>) -> Vec2<i32> 
+        {
+            self.current_map.cell_at(pos)
+        }
+    }
+
+    // Where a ray stopped, in world units, and the wall cell and face it stopped at
+    #[derive(Copy, Clone)]
+    pub struct RayHit 
+    {
+        pub position: Vec2<f32>,
+        pub cell: Vec2<i32>,
+        pub axis: Axis,
+    }
+
+    // What fills a map cell. Pillar is a round wall in the middle of the cell, its radius a fraction 
+    // of the cell's shorter side
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    pub enum CellShape 
+    {
+        Empty,
+        Wall,
+        Pillar(f32),
+    }
+
+    // Sums of the rays that land in one screen column, averaged when the column is drawn
+    #[derive(Default)]
+    struct ColumnSamples 
+    {
+        count: u32,
+        ray_distance: f32,
+        perpendicular: f32,
+        brightness: f32,
+    }
+
+    impl ColumnSamples 
+    {
+        fn add(&mut self,
+               ray_distance: f32,
+               perpendicular: f32,
+               brightness: f32) 
+        {
+            self.count += 1;
+            self.ray_distance += ray_distance;
+            self.perpendicular += perpendicular;
+            self.brightness += brightness;
+        }
+
+        // Averages of the ray distance, perpendicular distance and brightness, starts over after
+        fn take(&mut self) -> (f32, f32, f32) 
+        {
+            let count = self.count.max(1) as f32;
+            let averages = (self.ray_distance / count, self.perpendicular / count, self.brightness / count);
+            *self = ColumnSamples::default();
+
+            averages
+        }
+    }
+
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    pub enum Axis 
+    {
+        OnX,
+        OnY,
+    }
+
+    impl Axis 
+    {
+        // OnX walls run along y, OnY walls along x, the sign doesn't matter for reflect()
+        fn normal(&self) -> Vec2<f32> 
+        {
+            match self {
+                Axis::OnX => Vec2 { x: 1., y: 0. },
+                Axis::OnY => Vec2 { x: 0., y: 1. },
+            }
+        }
+    }
+
+    fn material_color(material: i32) -> Color 
+    {
+        match material 
+        {
+            1 => Color { r: 210, g: 180, b: 140 },
+            _ => DEFAULT_COLOR,
+        }
+    }
+
+    // Dim walls get the sparse characters from the start of the ramp, bright ones the dense ones
+    fn shade_char(brightness: f32,
+                  gamma: f32) -> u8 
+    {
+        let index = (brightness.clamp(0., 1.).powf(gamma) * SHADING_RAMP.len() as f32) as usize;
+
+        SHADING_RAMP[index.min(SHADING_RAMP.len() - 1)]
+    }
+
+    // Only the part of the strip that's on the screen gets sampled
+    fn draw_textured_strip(output: &mut Renderer,
+                           up: Vec2<f32>,
+                           down: Vec2<f32>,
+                           texture: &Texture,
+                           u: f32) 
+    {
+        let height = down.y - up.y;
+        let first = up.y.max(0.) as i32;
+        let last = down.y.min(output.get_screen_dim().y as f32) as i32;
+
+        if height <= 0. {
+            return;
+        }
+
+        for y in first..last 
+        {
+            let v = (y as f32 - up.y) / height;
+            output.draw_point(Vec2 { x: up.x as i32, y }, texture.sample(u, v));
+        }
+    }
+
+    // Top and bottom screen y of a wall strip seen from distance, centered on the horizon. 
+    // Scale comes from Game::projection_scale(). Near walls reach past the screen edges, 
+    // so callers clamp before drawing
+    fn wall_strip(distance: f32,
+                  scale: f32,
+                  horizon: f32) -> (f32, f32) 
+    {
+        let line_height = scale / distance.max(f32::EPSILON);
+
+        (horizon - line_height / 2., horizon + line_height / 2.)
+    }
+
+    // One of 8 sectors, yaw 0 faces north (up the map) and east is a quarter turn clockwise
+    fn compass_label(yaw: f32) -> &'static str 
+    {
+        const LABELS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+        const SECTOR: f32 = TWO_PI / 8.;
+
+        let sector = (normalize_angle(yaw + SECTOR / 2.) / SECTOR) as usize;
+
+        LABELS[sector % LABELS.len()]
+    }
+
+    fn normalize_angle(mut angle: f32) -> f32 
+    {
+        while angle < 0. {
+            angle += TWO_PI;
+        }
+        while angle > TWO_PI {
+            angle -= TWO_PI;
+        }
+        angle
+    }
+
+    // Error is how far the result is pushed over the border, so the next step starts in the 
+    // neighboring square, see Camera::boundary_epsilon. The flag is set when the border was hit 
+    // within error of a corner, the push then goes over both borders into the diagonal square
+    fn cast_ray(starting_pos: &Vec2<f32>,
+                angle: &f32,
+                boundry_top_left: &Vec2<f32>,
+                x_boundry: &f32,
+                y_boundry: &f32,
+                error: f32) -> (Vec2<f32>, Axis, bool) 
+    {
+        // Preallocate variables
+        let mut a: f32;
+        let mut o: f32;
+        let y_res: Vec2<f32>;
+        let x_res: Vec2<f32>;
+        let mut final_pos: Vec2<f32>;
+        let final_axis: Axis;
+        let corner: bool;
+        let hit_on_f_y: bool;
+        let hit_on_f_x: bool;
+
+        let current_relative_pos = Vec2::<f32> {
+            x: starting_pos.x - boundry_top_left.x,
+            y: starting_pos.y - boundry_top_left.y,
+        };
+
+        // Decide should we calculate top or bottom ray for the y axis
+
+        // Its top
+        if !(*angle > HALF_PI && *angle < PI + HALF_PI) 
+        {
+            a = current_relative_pos.y;
+            o = angle.tan() * a;
+
+            y_res = Vec2 {
+                x: starting_pos.x + o,
+                y: starting_pos.y - current_relative_pos.y,
+            };
+
+            hit_on_f_y = true;
+        }
+        // Its bottom
+        else 
+        {
+            a = y_boundry - current_relative_pos.y;
+            o = (angle + PI).tan() * a;
+
+            y_res = Vec2 {
+                x: starting_pos.x - o,
+                y: starting_pos.y - current_relative_pos.y + y_boundry,
+            };
+
+            hit_on_f_y = false;
+        }
+
+        // Decide should we calculate right or left ray for the x axis
+
+        // Its right 
+        if *angle < PI && *angle > 0. 
+        {
+            a = x_boundry - current_relative_pos.x;
+            o = (angle - HALF_PI).tan() * a;
+
+            x_res = Vec2 {
+                x: starting_pos.x - current_relative_pos.x + x_boundry,
+                y: starting_pos.y + o,
+            };
+
+            hit_on_f_x = true;
+        }
+        // Its left
+        else 
+        {
+            a = current_relative_pos.x;
+            o = (angle - PI - HALF_PI).tan() * a;
+
+            x_res = Vec2 {
+                x: starting_pos.x - current_relative_pos.x,
+                y: starting_pos.y - o,
+            };
+
+            hit_on_f_x = false;
+        }
+
+        // Decide which result is correct and fits in boundries
+        if y_res.x >= boundry_top_left.x &&
+           y_res.x <= boundry_top_left.x + x_boundry 
+        {
+                final_pos = y_res;
+                final_axis = Axis::OnY;
+
+                // Both candidates are the same point there, on the x border too
+                let x_border = if hit_on_f_x { boundry_top_left.x + x_boundry } else { boundry_top_left.x };
+                corner = (y_res.x - x_border).abs() < error;
+        }
+        else {
+            final_pos = x_res;
+            final_axis = Axis::OnX;
+
+            let y_border = if hit_on_f_y { boundry_top_left.y } else { boundry_top_left.y + y_boundry };
+            corner = (x_res.y - y_border).abs() < error;
+        } 
+
+        // Jump over square border
+        if hit_on_f_y { 
+            final_pos.y -= error;
+        }
+        else {
+            final_pos.y += error;
+        }
+
+        if hit_on_f_x { 
+            final_pos.x += error;
+        }
+        else {
+            final_pos.x -= error;
+        }
+
+        (final_pos, final_axis, corner)
+    }
+
+    #[cfg(test)]
+    mod tests 
+    {
+        use super::*;
+
+        // One string per row like a map file, digits are materials and O is a pillar. 
+        // The player starts wherever the built in map's spawn falls
+        fn game_with_map(rows: &[&str]) -> Game 
+        {
+            let mut topography = Vec::new();
+            let mut pillars = Vec::new();
+
+            for (y, row) in rows.iter().enumerate() 
+            {
+                for (x, ch) in row.chars().enumerate() 
+                {
+                    if ch == PILLAR_CELL {
+                        pillars.push(Vec2 { x: x as i32, y: y as i32 });
+                    }
+
+                    topography.push(ch.to_digit(10).map_or(1, |material| material as i32));
+                }
+            }
+
+            let mut map = Map::with_square_cells(topography, rows[0].len() as i32, rows.len() as i32, BUILT_IN_CELL_SIZE)
+                .unwrap();
+            for cell in pillars {
+                map.set_shape(cell.x, cell.y, CellShape::Pillar(DEFAULT_PILLAR_RADIUS)).unwrap();
+            }
+
+            let mut game = Game::new();
+            game.levels = vec![map];
+            game.load_level(0).unwrap();
+            game
+        }
+
+        #[test]
+        fn weapon_art_pads_short_rows() 
+        {
+            let weapon = Weapon::from_text(" |\n/|\\\n\n *\n/|\\").unwrap();
+
+            assert_eq!(weapon.width, 3);
+            assert_eq!(weapon.height(), 2);
+            assert_eq!(weapon.idle, [TRANSPARENT_CHAR, b'|', TRANSPARENT_CHAR, b'/', b'|', b'\\']);
+        }
+
+        #[test]
+        fn weapon_art_rejects_non_ascii() 
+        {
+            let err = Weapon::from_text("ab\n\u{e9}c\n\nab\ncd").err().unwrap();
+
+            assert_eq!(err, "Weapon art has to be ASCII, row 2 isn't");
+        }
+
+        // They agree when they stop in the same cell on the same axis, or close enough that only 
+        // the boundary epsilon's push tells them apart, or both run out of range
+        #[test]
+        fn march_ray_agrees_with_dda() 
+        {
+            let maps = [
+                Game::new(),
+                game_with_map(&["1111111111", 
+                                "1000000001", 
+                                "10O000O001", 
+                                "1000000001", 
+                                "1000000001", 
+                                "10O000O001", 
+                                "1111111111"]),
+                game_with_map(&["111111", 
+                                "100101", 
+                                "110001", 
+                                "100001", 
+                                "100011", 
+                                "111111"]),
+            ];
+
+            for game in maps.iter() 
+            {
+                // Twice the push, once over each of a corner's borders, and a thousandth of a cell for what 
+                // tan() loses in f32 on rays that are nearly along an axis
+                let tolerance = 2. * game.boundary_epsilon() + 0.001 * game.current_map.cell_size();
+                // The DDA stops right on the wall's border, pushed a bit further like march_ray() 
+                // the hit is inside of the wall's cell for both
+                let as_hit = |(position, axis, hit): (Vec2<f32>, Axis, bool), angle: f32| {
+                    let direction = Vec2::from_angle(angle);
+                    let inside = Vec2 { 
+                        x: position.x + direction.x * game.boundary_epsilon(), 
+                        y: position.y + direction.y * game.boundary_epsilon(),
+                    };
+
+                    hit.then(|| RayHit { position, cell: game.current_map.cell_at(inside), axis })
+                };
+
+                let mut disagreements = Vec::new();
+                for (origin, angle) in game.caster_rays(180) 
+                {
+                    let march = as_hit(game.march_ray(origin, angle), angle);
+                    let dda = as_hit(game.march_ray_dda(origin, angle), angle);
+
+                    let agree = match (march, dda) {
+                        (None, None) => true,
+                        (Some(a), Some(b)) => (a.cell.x == b.cell.x && a.cell.y == b.cell.y && a.axis == b.axis) 
+                                              || points_distance(a.position, b.position) <= tolerance,
+                        // One of them stopped right at the edge of the visible distance
+                        (Some(hit), None) | (None, Some(hit)) => {
+                            points_distance(origin, hit.position) >= game.max_view_distance() - game.current_map.cell_size()
+                        }
+                    };
+
+                    if !agree {
+                        disagreements.push(format!("origin {:.3}, {:.3} angle {:.5}: march {:?}, dda {:?}",
+                                                   origin.x, 
+                                                   origin.y, 
+                                                   angle, 
+                                                   march.map(|hit| (hit.cell.x, hit.cell.y, hit.axis)), 
+                                                   dda.map(|hit| (hit.cell.x, hit.cell.y, hit.axis))));
+                    }
+                }
+
+                assert!(disagreements.is_empty(), "{} rays disagree:\n{}", disagreements.len(), disagreements.join("\n"));
+            }
+        }
+
+        // A wide open map where the rays jump most of the way, every ray has to stop in the same 
+        // cell on the same axis as the one that crossed every cell, and as the DDA
+        #[test]
+        fn skipping_open_cells_keeps_the_hits() 
+        {
+            let rows: Vec<String> = (0..24)
+                .map(|y| (0..24)
+                    .map(|x| {
+                        let border = x == 0 || y == 0 || x == 23 || y == 23;
+                        if border || (x % 7 == 3 && y % 5 == 2) { '1' } else { '0' }
+                    })
+                    .collect())
+                .collect();
+            let game = game_with_map(&rows.iter().map(String::as_str).collect::<Vec<&str>>());
+            assert!(game.current_map.open_distance(10, 10) > 1);
+
+            let mut naive = game_with_map(&rows.iter().map(String::as_str).collect::<Vec<&str>>());
+            naive.current_map.open_distances = vec![0; naive.current_map.open_distances.len()];
+
+            let tolerance = 2. * game.boundary_epsilon() + 0.001 * game.current_map.cell_size();
+            // No diagonals, which go right through corners where the two casters may part ways
+            for (origin, angle) in game.caster_rays(23) 
+            {
+                let (skipped, skipped_axis, skipped_hit) = game.march_ray(origin, angle);
+                let (crossed, crossed_axis, crossed_hit) = naive.march_ray(origin, angle);
+                let (dda, dda_axis, _) = game.march_ray_dda(origin, angle);
+                let at = format!("origin {}, angle {}: {} and {}", origin, angle, skipped, crossed);
+
+                assert_eq!(skipped_hit, crossed_hit, "{}", at);
+                assert_eq!(skipped_axis, crossed_axis, "{}", at);
+                assert!(points_distance(skipped, crossed) <= tolerance, "{}", at);
+                assert!(skipped_axis == dda_axis || points_distance(skipped, dda) <= tolerance, "{}, dda {}", at, dda);
+            }
+        }
+
+        fn cell_center(cell: Vec2<i32>) -> Vec2<f32> 
+        {
+            Vec2 { 
+                x: (cell.x as f32 + 0.5) * BUILT_IN_CELL_SIZE, 
+                y: (cell.y as f32 + 0.5) * BUILT_IN_CELL_SIZE,
+            }
+        }
+
+        #[test]
+        fn spawn_has_to_be_open_and_not_an_exit() 
+        {
+            let mut game = game_with_map(&["11111", 
+                                           "10091", 
+                                           "10101", 
+                                           "11111"]);
+
+            assert!(game.set_spawn(cell_center(Vec2 { x: 2, y: 2 }), 0.).is_err());
+            assert!(game.set_spawn(cell_center(Vec2 { x: 3, y: 1 }), 0.).is_err());
+            assert!(game.set_spawn(cell_center(Vec2 { x: -1, y: 1 }), 0.).is_err());
+
+            let open = cell_center(Vec2 { x: 1, y: 2 });
+            game.set_spawn(open, 0.).unwrap();
+            game.respawn();
+            assert_eq!((game.main_player.actor.position.x, game.main_player.actor.position.y), (open.x, open.y));
+        }
+
+        #[test]
+        fn teleport_moves_to_open_cells_only() 
+        {
+            let mut game = game_with_map(&["11111", 
+                                           "10091", 
+                                           "10101", 
+                                           "11111"]);
+            let start = game.main_player.actor.position;
+
+            for cell in [Vec2 { x: 2, y: 2 }, Vec2 { x: 3, y: 1 }, Vec2 { x: 5, y: 1 }] 
+            {
+                assert!(game.teleport(cell_center(cell), 0.).is_err());
+                assert_eq!((game.main_player.actor.position.x, game.main_player.actor.position.y), (start.x, start.y));
+            }
+
+            let open = cell_center(Vec2 { x: 3, y: 2 });
+            game.teleport(open, 7. * PI / 2.).unwrap();
+            assert_eq!((game.main_player.actor.position.x, game.main_player.actor.position.y), (open.x, open.y));
+            assert!((game.main_player.actor.yaw - 3. * PI / 2.).abs() < 1e-4);
+        }
+
+        fn draw_rows(game: &mut Game, 
+                     mode: ViewMode, 
+                     width: i16, 
+                     height: i16) -> Vec<String> 
+        {
+            let mut output = Renderer::with_writer(Box::new(std::io::sink()));
+            output.set_dimensions(width, height * 2);
+            output.update();
+            game.calculate_and_draw(&mut output, &mode);
+            output.render();
+
+            let frame = output.snapshot();
+            frame.data()
+                .chunks(frame.width() as usize)
+                .map(|row| String::from_utf8_lossy(row).into_owned())
+                .collect()
+        }
+
+        // Each half is the same picture as that view drawn on a screen of its own size
+        #[test]
+        fn split_view_is_the_map_and_the_3d_view_side_by_side() 
+        {
+            let mut game = Game::new();
+
+            let split = draw_rows(&mut game, ViewMode::Mode2dAnd3d, 41, 20);
+            let map = draw_rows(&mut game, ViewMode::Mode2d, 20, 20);
+            let view = draw_rows(&mut game, ViewMode::Mode3d, 20, 20);
+            assert!(map.concat().contains(WALL_2D_CHAR as char));
+            assert!(view.concat().contains(CROSSHAIR_CHAR as char));
+
+            for (y, row) in split.iter().enumerate() 
+            {
+                assert_eq!(&row[..20], map[y], "row {}", y);
+                assert_eq!(row.as_bytes()[20], DIVIDER_CHAR, "row {}", y);
+                assert_eq!(&row[21..], view[y], "row {}", y);
+            }
+        }
+
+        // The middle column of the 3d half of a split view, 20 columns across, of a player two and 
+        // a half cells from the wall they face, with the rows the projection says the wall covers
+        fn split_view_middle_column(game: &mut Game) -> (String, Vec<usize>) 
+        {
+            let rows = draw_rows(game, ViewMode::Mode2dAnd3d, 41, 20);
+            let column: String = rows.iter().map(|row| row.as_bytes()[31] as char).collect();
+
+            // The middle column's ray is straight ahead, its length rounded up like in calculate_and_draw()
+            let distance = (2.5 * BUILT_IN_CELL_SIZE + game.boundary_epsilon()).ceil();
+            let screen = Vec2 { x: 20, y: 40 };
+            let (up, down) = wall_strip(distance, game.projection_scale(&screen), game.horizon(screen.y as f32));
+            // Lines leave out their last point
+            let covered = (up.max(0.) as usize / 2..=(down.min(39.) as usize - 1) / 2).collect();
+
+            (column, covered)
+        }
+
+        fn corridor() -> Game 
+        {
+            let mut game = game_with_map(&["11111", 
+                                           "10001", 
+                                           "10001", 
+                                           "10001", 
+                                           "11111"]);
+            game.teleport(cell_center(Vec2 { x: 2, y: 3 }), 0.).unwrap();
+            // Stays in the middle of the screen whatever the horizon does
+            game.set_crosshair(None);
+            game
+        }
+
+        #[test]
+        fn split_view_walls_follow_the_projection() 
+        {
+            let mut game = corridor();
+            let (column, covered) = split_view_middle_column(&mut game);
+            assert!(covered.len() > 1);
+
+            for (y, ch) in column.chars().enumerate() {
+                assert_eq!(ch != ' ', covered.contains(&y), "row {} of {:?}, wall on rows {:?}", y, column, covered);
+            }
+        }
+
+        // Looking down raises the horizon, the walls of the split view go with it
+        #[test]
+        fn split_view_walls_follow_the_horizon() 
+        {
+            let mut game = corridor();
+            let (level, _) = split_view_middle_column(&mut game);
+
+            game.camera.vertical_look = -0.2;
+            let (column, covered) = split_view_middle_column(&mut game);
+
+            assert!(covered.iter().max() < level.rfind(|ch| ch != ' ').as_ref(), "{:?} {:?}", level, covered);
+            for (y, ch) in column.chars().enumerate() {
+                assert_eq!(ch != ' ', covered.contains(&y), "row {} of {:?}, wall on rows {:?}", y, column, covered);
+            }
+        }
+
+        // A setting before the preset is replaced by it, one after it changes what it set
+        #[test]
+        fn config_look_starts_from_the_preset() 
+        {
+            let config = Config::parse("side_shade = 0.5\npreset = high_contrast\ndistance_fog = true\n").unwrap();
+            let mut output = Renderer::with_writer(Box::new(std::io::sink()));
+            let mut game = Game::new();
+
+            game.apply_look(&mut output, &config);
+
+            assert_eq!((game.palette.wall_x, game.palette.wall_y), RenderPreset::HighContrast.wall_chars());
+            assert_eq!(game.side_shade, RenderPreset::HighContrast.side_shade());
+            assert!(game.distance_fog);
+        }
+
+        #[test]
+        fn cell_shapes_replace_each_other() 
+        {
+            let mut map = Map::with_square_cells(vec![1, 1, 1, 1, 0, 1, 1, 1, 1], 3, 3, BUILT_IN_CELL_SIZE).unwrap();
+
+            map.set_shape(1, 1, CellShape::Pillar(0.25)).unwrap();
+            assert_eq!(map.shape(1, 1), CellShape::Pillar(0.25));
+            assert_eq!(map.shape(0, 1), CellShape::Wall);
+
+            map.set_shape(1, 1, CellShape::Wall).unwrap();
+            assert_eq!(map.shape(1, 1), CellShape::Wall);
+            map.set_shape(1, 1, CellShape::Empty).unwrap();
+            assert_eq!(map.shape(1, 1), CellShape::Empty);
+            assert_eq!(map.shape(-1, 1), CellShape::Wall);
+            assert!(map.set_shape(3, 1, CellShape::Wall).is_err());
+        }
+
+        // Rendering is drawing only, the same picture every time and nothing marked as seen
+        #[test]
+        fn render_leaves_the_game_alone() 
+        {
+            let mut game = Game::new();
+            game.set_fog_of_war(true);
+            let is_explored = |game: &Game| (0..game.current_map.topography_y)
+                .flat_map(|y| (0..game.current_map.topography_x).map(move |x| Vec2 { x, y }))
+                .filter(|cell| game.is_explored(*cell))
+                .count();
+
+            let first = draw_rows(&mut game, ViewMode::Mode3d, 40, 20);
+            assert_eq!(draw_rows(&mut game, ViewMode::Mode3d, 40, 20), first);
+            assert_eq!(is_explored(&game), 0);
+
+            game.step(KeyState::default(), 0.016);
+            assert!(is_explored(&game) > 0);
+        }
+
+        // The end of the corridor around the corner only gets explored once the player 
+        // walked up to the corner and turned to look down it
+        #[test]
+        fn walking_explores_what_comes_into_view() 
+        {
+            let mut game = game_with_map(&["1111111", 
+                                           "1000001", 
+                                           "1011111", 
+                                           "1011111", 
+                                           "1011111", 
+                                           "1111111"]);
+            game.teleport(cell_center(Vec2 { x: 1, y: 4 }), 0.).unwrap();
+            game.set_fog_of_war(true);
+            let around_the_corner = Vec2 { x: 5, y: 1 };
+
+            game.step(KeyState::default(), 0.016);
+            assert!(game.is_explored(Vec2 { x: 1, y: 4 }));
+            assert!(game.is_explored(Vec2 { x: 1, y: 2 }));
+            assert!(!game.is_explored(around_the_corner));
+
+            for _ in 0..200 
+            {
+                if game.calculate_current_square(game.main_player.actor.position).y == 1 {
+                    break;
+                }
+                game.step(KeyState::default().with(Key::W), 0.016);
+            }
+            assert_eq!(game.calculate_current_square(game.main_player.actor.position).y, 1);
+            assert!(!game.is_explored(around_the_corner));
+
+            // A quarter turn in one step
+            game.step(KeyState::default().with(Key::E), HALF_PI / PLAYER_ROTATION_SPEED);
+            assert!(game.is_explored(around_the_corner));
+            assert!(game.is_explored(Vec2 { x: 4, y: 1 }));
+        }
+
+        // A camera away from the player sees what the player would from there, 
+        // but only what the player sees gets explored
+        #[test]
+        fn detached_camera_renders_from_its_own_position() 
+        {
+            let rooms = ["1111111", 
+                         "1001001", 
+                         "1001001", 
+                         "1111111"];
+            let mut game = game_with_map(&rooms);
+            game.teleport(cell_center(Vec2 { x: 1, y: 2 }), 0.).unwrap();
+            game.set_fog_of_war(true);
+            game.set_camera(cell_center(Vec2 { x: 4, y: 2 }), 90. * RADIAN);
+
+            let mut there = game_with_map(&rooms);
+            there.teleport(cell_center(Vec2 { x: 4, y: 2 }), 90. * RADIAN).unwrap();
+
+            game.step(KeyState::default(), 0.016);
+            assert_eq!(game.camera_position().x, cell_center(Vec2 { x: 4, y: 2 }).x);
+            assert_eq!(draw_rows(&mut game, ViewMode::Mode3d, 40, 20), draw_rows(&mut there, ViewMode::Mode3d, 40, 20));
+
+            assert!(game.is_explored(Vec2 { x: 1, y: 2 }));
+            assert!(!game.is_explored(Vec2 { x: 4, y: 2 }));
+            assert!(!game.is_explored(Vec2 { x: 5, y: 1 }));
+        }
+
+        // Every ray of a column counts the same in both, not only the last one
+        #[test]
+        fn wireframe_averages_supersampled_rays_like_3d() 
+        {
+            let mut game = Game::new();
+            game.set_ray_count(40 * 4);
+
+            draw_rows(&mut game, ViewMode::Mode3d, 40, 20);
+            let depth = game.depth_buffer.clone();
+            draw_rows(&mut game, ViewMode::Wireframe, 40, 20);
+
+            assert_eq!(game.depth_buffer, depth);
+        }
+
+        // The built in map's spawn is open, its walls and the cells outside of it can't be spawned in
+        #[test]
+        fn config_spawn_is_checked_against_the_map() 
+        {
+            let spawn_in = |line: &str| Config::parse(line).and_then(|config| Game::with_config(&config).map(|_| ()));
+
+            assert!(spawn_in("spawn = 37.5, 37.5\nspawn_yaw = 90").is_ok());
+            assert!(spawn_in("spawn = 12.5, 12.5").unwrap_err().starts_with("spawn in the config: "));
+            assert!(spawn_in("spawn = 100000, 12.5").is_err());
+            assert!(spawn_in("spawn = -5, 12.5").is_err());
+            assert!(spawn_in("spawn = 5").is_err());
+        }
+
+        // Two frames of 2 ticks each, the walls only change once the clock has gone 2 ticks further
+        #[test]
+        fn config_textures_animate_with_the_ticks() 
+        {
+            let path = std::env::temp_dir().join(format!("wolfenstein_texture_{}.txt", std::process::id()));
+            std::fs::write(&path, "#\n\n=\n").unwrap();
+            let config = Config::parse(&format!("texture_1 = \"{}\", 2", path.display())).unwrap();
+            let game = Game::with_config(&config);
+            std::fs::remove_file(&path).unwrap();
+            let mut game = game.unwrap();
+
+            let clock = MockClock::new();
+            game.set_clock(Box::new(clock.clone()));
+            let first = draw_rows(&mut game, ViewMode::Mode3d, 40, 20);
+            assert!(first.iter().any(|row| row.contains('#')));
+
+            clock.advance(game.tick_duration);
+            let dt = game.frame_dt();
+            game.step(KeyState::default(), dt);
+            assert_eq!(game.texture_frame(1), Some(0));
+            assert_eq!(draw_rows(&mut game, ViewMode::Mode3d, 40, 20), first);
+
+            clock.advance(game.tick_duration);
+            let dt = game.frame_dt();
+            game.step(KeyState::default(), dt);
+            assert_eq!(game.texture_frame(1), Some(1));
+            let second = draw_rows(&mut game, ViewMode::Mode3d, 40, 20);
+            assert_eq!(second, first.iter().map(|row| row.replace('#', "=")).collect::<Vec<String>>());
+        }
+
+        #[test]
+        fn texture_art_splits_frames_on_empty_lines() 
+        {
+            let frames = Texture::from_text("ab\nc\n\n\nde\nfg\n").unwrap();
+
+            assert_eq!(frames.len(), 2);
+            assert_eq!(frames[0].data, b"abc ");
+            assert!(Texture::from_text("\n\n").is_err());
+            assert!(Config::parse("texture_9 = exit.txt").is_err());
+            assert!(Config::parse("texture_1 = wall.txt, 0").is_err());
+        }
+
+        // Forward frames of the script step north, the empty one and the ones after it ran out stand still
+        #[test]
+        fn scripted_input_drives_the_player() 
+        {
+            use crate::terminal::input::{InputSource, ScriptedInput};
+
+            let mut game = corridor();
+            let start = game.main_player.actor.position;
+            let forward = KeyState::default().with(Key::W);
+            let mut script = ScriptedInput::new(vec![forward, forward, KeyState::default(), forward, forward]);
+
+            let mut ys = Vec::new();
+            for _ in 0..8 
+            {
+                game.step(script.poll(), 0.016);
+                let position = game.main_player.actor.position;
+
+                assert!((position.x - start.x).abs() < 0.01, "{} to {}", start.x, position.x);
+                ys.push(position.y);
+            }
+
+            assert!(script.is_done());
+            assert!(start.y > ys[0] && ys[0] > ys[1] && ys[2] > ys[3] && ys[3] > ys[4], "{} then {:?}", start.y, ys);
+            assert_eq!(ys[1], ys[2]);
+            assert!(ys[4..].iter().all(|&y| y == ys[4]));
+            // Four steps are not enough to reach the middle of the next cell
+            assert!(ys[4] > cell_center(Vec2 { x: 2, y: 2 }).y);
+        }
+
+        // Intensity 2 settles in half a second, the view jitters by no more than what's left of it
+        #[test]
+        fn shake_settles_over_intensity_over_shake_settle_seconds() 
+        {
+            let mut game = Game::new();
+            game.add_shake(2.);
+            game.add_shake(1.);
+            assert_eq!(game.shake(), 2.);
+
+            game.step(KeyState::default(), 2. / SHAKE_SETTLE / 2.);
+            assert!((game.shake() - 1.).abs() < 1e-5, "{}", game.shake());
+            assert!(game.shake_offset.x.abs() <= 1. && game.shake_offset.y.abs() <= 1.);
+
+            game.step(KeyState::default(), 2. / SHAKE_SETTLE / 2.);
+            assert_eq!(game.shake(), 0.);
+            assert_eq!((game.shake_offset.x, game.shake_offset.y), (0., 0.));
+
+            game.set_screen_shake(false);
+            game.add_shake(2.);
+            assert_eq!(game.shake(), 0.);
+        }
+
+        // Straight at the corner between two walls touching diagonally, the ray stops on 
+        // the one across the y border instead of slipping into the open cell between them
+        #[test]
+        fn ray_at_a_convex_corner_stops_on_it() 
+        {
+            let game = game_with_map(&["11111", 
+                                       "10101", 
+                                       "11001", 
+                                       "10001", 
+                                       "11111"]);
+            let origin = cell_center(Vec2 { x: 1, y: 1 });
+
+            let (hit_pos, axis, _) = game.march_ray(origin, 135. * RADIAN);
+            let cell = game.calculate_current_square(hit_pos);
+            assert_eq!((cell.x, cell.y), (1, 2), "{}", hit_pos);
+            assert!(matches!(axis, Axis::OnY));
+            assert!(points_distance(hit_pos, Vec2 { x: 50., y: 50. }) < 0.01, "{}", hit_pos);
+
+            // A hair to either side it stops on one of the two walls all the same
+            for angle in [134.99, 135.01] 
+            {
+                let (hit_pos, _, _) = game.march_ray(origin, angle * RADIAN);
+                let cell = game.calculate_current_square(hit_pos);
+                assert!((cell.x, cell.y) == (1, 2) || (cell.x, cell.y) == (2, 1), "{} at {}", hit_pos, angle);
+            }
+        }
+
+        // Halving tan(vertical_fov / 2) or the cell aspect puts the plane twice as far, 
+        // a wall the same distance away comes out twice as tall
+        #[test]
+        fn twice_the_projection_distance_twice_the_wall() 
+        {
+            let screen = Vec2 { x: 80, y: 48 };
+            let wall_height = |game: &Game| {
+                let (up, down) = wall_strip(60., game.projection_scale(&screen), game.horizon(screen.y as f32));
+                down - up
+            };
+            let close = |a: f32, b: f32| (a - b).abs() < 1e-3 * b;
+
+            let mut near = Game::new();
+            near.set_vertical_fov(2. * 0.5f32.atan() / RADIAN);
+            let mut far = Game::new();
+            far.set_vertical_fov(2. * 0.25f32.atan() / RADIAN);
+
+            assert!(close(far.projection_plane_distance(&screen), 2. * near.projection_plane_distance(&screen)));
+            assert!(close(wall_height(&far), 2. * wall_height(&near)));
+
+            let mut wide = Game::new();
+            wide.set_cell_aspect(DEFAULT_CELL_ASPECT / 2.);
+            assert!(close(wide.projection_plane_distance(&screen), 2. * Game::new().projection_plane_distance(&screen)));
+            assert!(close(wall_height(&wide), 2. * wall_height(&Game::new())));
+        }
+
+        #[test]
+        fn map_dimensions_have_to_match_the_topography() 
+        {
+            assert!(Map::new(vec![1; 6], 3, 2, 25., 25.).is_ok());
+            assert_eq!(Map::new(vec![1; 6], 4, 2, 25., 25.).err().unwrap(), "Map of 4x2 cells has 6 cells of topography");
+            assert!(Map::new(vec![1; 6], 2, 2, 25., 25.).is_err());
+            assert!(Map::new(vec![1; 6], 6, 0, 25., 25.).is_err());
+            assert!(Map::new(vec![1; 6], -3, -2, 25., 25.).is_err());
+            assert!(Map::new(Vec::new(), 0, 0, 25., 25.).is_err());
+
+            let map = Map::new(vec![1, 2, 3, 4, 5, 6], 3, 2, 25., 25.).unwrap();
+            assert_eq!(map.get(2, 1), Some(6));
+            assert_eq!(map.get(3, 0), None);
+            assert_eq!(map.get(0, 2), None);
+            assert_eq!(map.get(-1, 0), None);
+        }
+
+        // The first open cell of the border row by row, an exit in the border is a hole too
+        #[test]
+        fn open_border_cells_are_found() 
+        {
+            let map = |rows: &[&str]| game_with_map(rows).current_map;
+
+            let enclosed = map(&["11111", 
+                                 "10091", 
+                                 "11111"]);
+            assert!(enclosed.is_enclosed());
+            assert!(map(&["1111", 
+                          "1009", 
+                          "1111"]).open_border_cell().is_some());
+
+            let holes = map(&["1111", 
+                              "1000", 
+                              "1101"]);
+            let hole = holes.open_border_cell().unwrap();
+            assert_eq!((hole.x, hole.y), (3, 1));
+            assert!(!holes.is_enclosed());
+        }
+
+        // Every label covers the 45 degrees around its direction, clockwise from north
+        #[test]
+        fn compass_labels_of_the_yaw() 
+        {
+            let label = |degrees: f32| compass_label(degrees * RADIAN);
+
+            assert_eq!(label(0.), "N");
+            assert_eq!(label(45.), "NE");
+            assert_eq!(label(90.), "E");
+            assert_eq!(label(135.), "SE");
+            assert_eq!(label(180.), "S");
+            assert_eq!(label(225.), "SW");
+            assert_eq!(label(270.), "W");
+            assert_eq!(label(315.), "NW");
+            assert_eq!(label(360.), "N");
+            assert_eq!(label(20.), "N");
+            assert_eq!(label(25.), "NE");
+            assert_eq!(label(-20.), "N");
+            assert_eq!(label(-30.), "NW");
+            assert_eq!(label(720. + 91.), "E");
+        }
+
+        // Walking onto the exit starts the next level at its spawn, facing its way
+        #[test]
+        fn walking_into_an_exit_loads_the_next_level() 
+        {
+            let mut game = game_with_map(&["11111", 
+                                           "11911", 
+                                           "11011", 
+                                           "11011", 
+                                           "11111"]);
+            let mut next = game_with_map(&["1111", 
+                                           "1001", 
+                                           "1001", 
+                                           "1111"]).current_map;
+            next.spawn = cell_center(Vec2 { x: 2, y: 2 });
+            next.spawn_yaw = HALF_PI;
+            game.levels.push(next);
+            game.teleport(cell_center(Vec2 { x: 2, y: 3 }), 0.).unwrap();
+
+            for _ in 0..200 
+            {
+                if game.level == 1 {
+                    break;
+                }
+                game.step(KeyState::default().with(Key::W), 0.016);
+            }
+
+            assert_eq!(game.level, 1);
+            assert_eq!(game.current_map.topography_x, 4);
+            let position = game.player_position();
+            assert_eq!((position.x, position.y), (62.5, 62.5));
+            assert_eq!(game.player_yaw(), HALF_PI);
+        }
+
+        // Walking into a wall stops in front of it, at an angle the player slides along it. 
+        // Noclip walks on through it until the edge of the map
+        #[test]
+        fn walls_stop_the_player_unless_noclip() 
+        {
+            let mut game = game_with_map(&["11111", 
+                                           "10001", 
+                                           "10001", 
+                                           "10001", 
+                                           "11111"]);
+            game.set_allow_noclip(true);
+            let forward = KeyState::default().with(Key::W);
+
+            game.teleport(cell_center(Vec2 { x: 2, y: 3 }), 0.).unwrap();
+            for _ in 0..100 {
+                game.step(forward, 0.05);
+            }
+
+            let stopped = game.player_position();
+            assert!(stopped.y >= BUILT_IN_CELL_SIZE && stopped.y < 1.1 * BUILT_IN_CELL_SIZE, "{}", stopped);
+            assert!((stopped.x - 62.5).abs() < 0.1, "{}", stopped);
+
+            game.teleport(cell_center(Vec2 { x: 1, y: 3 }), 0.3).unwrap();
+            for _ in 0..200 {
+                game.step(forward, 0.05);
+            }
+
+            let slid = game.player_position();
+            assert!(slid.y >= BUILT_IN_CELL_SIZE, "{}", slid);
+            assert_eq!(game.current_map.cell_at(slid).x, 3, "{}", slid);
+
+            game.teleport(cell_center(Vec2 { x: 2, y: 3 }), 0.).unwrap();
+            game.noclip = true;
+            for _ in 0..100 {
+                game.step(forward, 0.05);
+            }
+
+            let through = game.player_position();
+            assert_eq!((through.x, through.y), (62.5, 0.));
+        }
+
+        // Walking goes by the frame time like turning does, and slowing time slows it down
+        #[test]
+        fn walking_distance_follows_dt_and_time_scale() 
+        {
+            let forward = KeyState::default().with(Key::W);
+            let walked = |steps: &[f32], time_scale: f32| {
+                let mut game = game_with_map(&["111", 
+                                               "101", 
+                                               "101", 
+                                               "101", 
+                                               "111"]);
+                game.set_time_scale(time_scale);
+                game.teleport(cell_center(Vec2 { x: 1, y: 3 }), 0.).unwrap();
+
+                for dt in steps {
+                    game.step(forward, *dt);
+                }
+
+                cell_center(Vec2 { x: 1, y: 3 }).y - game.player_position().y
+            };
+
+            let whole = walked(&[0.05], 1.);
+            assert!((whole - PLAYER_MOVEMENT_SPEED * 0.05).abs() < 0.001, "{}", whole);
+            assert!((walked(&[0.025, 0.025], 1.) - whole).abs() < 0.001);
+            assert!((walked(&[0.05], 0.5) - whole / 2.).abs() < 0.001);
+        }
+
+        // Turning and walking at once go by the same dt, split frames end up where a whole one does
+        #[test]
+        fn turning_and_walking_split_the_same_over_frames() 
+        {
+            let keys = KeyState::default().with(Key::W).with(Key::E);
+            let moved = |steps: &[f32]| {
+                let mut game = game_with_map(&["11111", 
+                                               "10001", 
+                                               "10001", 
+                                               "10001", 
+                                               "11111"]);
+                game.teleport(cell_center(Vec2 { x: 2, y: 2 }), 0.).unwrap();
+
+                for dt in steps {
+                    game.step(keys, *dt);
+                }
+
+                let start = cell_center(Vec2 { x: 2, y: 2 });
+                (points_distance(start, game.player_position()), game.player_yaw())
+            };
+
+            let (walked, turned) = moved(&[0.1]);
+            let (split_walked, split_turned) = moved(&[0.05, 0.05]);
+
+            assert!((turned - PLAYER_ROTATION_SPEED * 0.1).abs() < 0.0001, "{}", turned);
+            assert!((split_turned - turned).abs() < 0.0001);
+            assert!((walked - PLAYER_MOVEMENT_SPEED * 0.1).abs() < 0.001, "{}", walked);
+            assert!((split_walked - walked).abs() < 0.01, "{} {}", split_walked, walked);
+        }
+
+        // The same seed sprays the same sparks, another one different ones
+        #[test]
+        fn seeded_games_spray_the_same_sparks() 
+        {
+            let sparks = |seed: u64| {
+                let mut game = Game::with_seed(seed);
+                assert!(game.fire().is_some());
+                game.step(KeyState::default(), 0.05);
+
+                game.particles.iter()
+                    .map(|p| (p.pos.x, p.pos.y, p.vel.x, p.vel.y))
+                    .collect::<Vec<_>>()
+            };
+
+            let first = sparks(3);
+            assert!(!first.is_empty());
+            assert_eq!(first, sparks(3));
+            assert_ne!(first, sparks(4));
+        }
+
+        // A waypoint behind a wall has the autopilot walk into the wall, not through it
+        #[test]
+        fn autopilot_stops_at_walls_in_the_way() 
+        {
+            let mut game = game_with_map(&["11111", 
+                                           "10001", 
+                                           "11101", 
+                                           "10001", 
+                                           "11111"]);
+            game.teleport(cell_center(Vec2 { x: 1, y: 3 }), 0.).unwrap();
+            game.set_autopilot(vec![cell_center(Vec2 { x: 1, y: 1 })]);
+
+            for _ in 0..200 
+            {
+                game.step(KeyState::default(), 0.05);
+
+                let cell = game.current_map.cell_at(game.player_position());
+                assert_eq!(cell.y, 3, "{}", game.player_position());
+                assert!(!game.current_map.is_solid(cell.x, cell.y), "{}", game.player_position());
+            }
+        }
+
+        // Only an exit ahead and in range counts, and only while there's a level to go to
+        #[test]
+        fn interact_target_is_the_exit_ahead() 
+        {
+            let mut game = game_with_map(&["11111", 
+                                           "11911", 
+                                           "11011", 
+                                           "11011", 
+                                           "11111"]);
+            assert!(game.interact_target().is_none());
+
+            game.levels.push(game.current_map.clone());
+            game.teleport(cell_center(Vec2 { x: 2, y: 2 }), 0.).unwrap();
+            let exit = game.interact_target().unwrap();
+            assert_eq!((exit.x, exit.y), (2, 1));
+
+            game.teleport(cell_center(Vec2 { x: 2, y: 2 }), PI).unwrap();
+            assert!(game.interact_target().is_none());
+
+            game.teleport(cell_center(Vec2 { x: 2, y: 3 }), 0.).unwrap();
+            game.interact_range = 1.;
+            assert!(game.interact_target().is_none());
+            game.interact_range = 2.;
+            assert!(game.interact_target().is_some());
+        }
+
+        // The header has the size in cells and then comes a line per row of the screen
+        #[test]
+        fn dump_view_starts_with_the_dimensions() 
+        {
+            let mut game = corridor();
+            let mut output = Renderer::with_writer(Box::new(std::io::sink()));
+            output.set_dimensions(30, 24);
+            output.update();
+            game.calculate_and_draw(&mut output, &ViewMode::Mode3d);
+            output.render();
+
+            let dump = game.dump_view(&output);
+            let lines: Vec<&str> = dump.lines().collect();
+
+            assert_eq!(lines[0], "# 30x12 cells");
+            assert_eq!(lines[1], "# position 62.50, 87.50 yaw 0.0 pitch 0.00 level 1");
+            assert_eq!(lines.len(), 2 + 12);
+            assert!(lines[2..].iter().all(|line| line.len() == 30));
+        }
+
+        // Nothing to cast into, the views show as much of the message as fits
+        #[test]
+        fn one_cell_screen_gets_the_too_small_message() 
+        {
+            let mut game = Game::new();
+
+            for mode in [ViewMode::Mode3d, ViewMode::Mode2d, ViewMode::Mode2dAnd3d, ViewMode::Wireframe] 
+            {
+                // The split view's halves get no columns at all, only its divider is left
+                let shown = if mode == ViewMode::Mode2dAnd3d { "|" } else { &TOO_SMALL_MESSAGE[..1] };
+                assert_eq!(draw_rows(&mut game, mode, 1, 1), [shown]);
+
+                // What a console can report in the middle of being resized
+                let mut output = Renderer::with_writer(Box::new(std::io::sink()));
+                output.set_dimensions(0, 0);
+                game.update_with_dt(&mut output, KeyState::default(), mode, 0.016);
+                assert!(output.snapshot().is_empty());
+            }
+
+            game.camera.fov = 0.;
+            game.camera.max_visible_distance = 0;
+            assert_eq!(draw_rows(&mut game, ViewMode::Mode3d, 1, 1), [&TOO_SMALL_MESSAGE[..1]]);
+        }
+
+        // The view is rebuilt for every new size, the player stays where they were
+        #[test]
+        fn resizing_between_frames_rebuilds_the_view() 
+        {
+            let mut game = Game::new();
+            let mut output = Renderer::with_writer(Box::new(std::io::sink()));
+            let start = game.player_position();
+
+            for (width, height) in [(40, 40), (80, 20), (7, 60), (120, 4), (40, 40)] 
+            {
+                for mode in [ViewMode::Mode3d, ViewMode::Mode2dAnd3d, ViewMode::Wireframe] 
+                {
+                    output.set_dimensions(width, height);
+                    game.update_with_dt(&mut output, KeyState::default(), mode, 0.016);
+
+                    let frame = output.snapshot();
+                    assert_eq!((frame.width(), frame.height()), (width, height / 2));
+                    assert_eq!(frame.len(), width as usize * (height / 2) as usize);
+                    // The split view's 3d half is right of the divider
+                    let cast = match mode {
+                        ViewMode::Mode2dAnd3d => width - width / 2 - 1,
+                        _ => width,
+                    };
+                    assert_eq!(game.depth_buffer.len(), cast as usize);
+                }
+            }
+
+            assert_eq!((game.player_position().x, game.player_position().y), (start.x, start.y));
+        }
+
+        // A 90 degree fov over 80 columns, facing north from 100, 100
+        #[test]
+        fn project_point_ahead_at_the_edges_and_behind() 
+        {
+            let camera = Game::new().camera;
+            let position = Vec2 { x: 100., y: 100. };
+            let at_degrees = |degrees: f32| {
+                let direction = Vec2::from_angle(degrees * RADIAN);
+                camera.project_point(position, 0., Vec2 { x: 100. + direction.x * 50., y: 100. + direction.y * 50. }, 80)
+            };
+
+            assert_eq!(camera.fov, 90.);
+            assert_eq!(camera.project_point(position, 0., Vec2 { x: 100., y: 50. }, 80), Some((40, 50.)));
+
+            let (right, distance) = at_degrees(44.).unwrap();
+            assert_eq!(right, 79);
+            assert!((distance - 50. * (44. * RADIAN).cos()).abs() < 1e-3);
+            assert_eq!(at_degrees(-44.).map(|(column, _)| column), Some(0));
+
+            assert_eq!(at_degrees(46.), None);
+            assert_eq!(at_degrees(-46.), None);
+            assert_eq!(at_degrees(180.), None);
+            assert_eq!(camera.project_point(position, 0., Vec2 { x: 100., y: 150. }, 80), None);
+            // Facing south the same point is straight ahead
+            assert_eq!(camera.project_point(position, PI, Vec2 { x: 100., y: 150. }, 80), Some((40, 50.)));
+        }
+
+        // The ray passes 5 units over the corner of the wall in the middle. Pushed a tenth of a cell over every 
+        // border it lands in that wall. The default push gets it to the far wall like the DDA
+        #[test]
+        fn boundary_epsilon_between_its_failure_modes() 
+        {
+            let rows = ["1111111111", 
+                        "1000000001", 
+                        "1001000001", 
+                        "1111111111"];
+            let origin = cell_center(Vec2 { x: 1, y: 1 });
+            let angle = 96.5 * RADIAN;
+            let cast = |epsilon: f32, angle: f32| {
+                let mut game = game_with_map(&rows);
+                game.set_boundary_epsilon(epsilon);
+                let (position, _, hit) = game.march_ray(origin, angle);
+                (game.calculate_current_square(position), hit)
+            };
+
+            let game = game_with_map(&rows);
+            let (dda, _, _) = game.march_ray_dda(origin, angle);
+            assert!(dda.x > 9. * BUILT_IN_CELL_SIZE - 1., "{}", dda);
+
+            let (cell, hit) = cast(DEFAULT_BOUNDARY_EPSILON, angle);
+            assert!(hit);
+            assert_eq!((cell.x, cell.y), (9, 2));
+
+            let (cell, hit) = cast(MAX_BOUNDARY_EPSILON, angle);
+            assert!(hit);
+            assert_eq!((cell.x, cell.y), (3, 2));
+
+            // Straight up at the wall next to it, with no push the ray never gets off the border
+            assert!(cast(DEFAULT_BOUNDARY_EPSILON, 0.).1);
+            assert!(!cast(0., 0.).1);
+        }
+
+        // Clones share the time, the game measures frames on the one it was given
+        #[test]
+        fn frame_dt_goes_by_the_mock_clock() 
+        {
+            let clock = MockClock::new();
+            clock.advance(Duration::from_secs(5));
+            let mut game = Game::new();
+            game.set_clock(Box::new(clock.clone()));
+
+            assert_eq!(game.frame_dt(), 0.);
+            clock.advance(Duration::from_millis(250));
+            assert_eq!(game.frame_dt(), 0.25);
+            assert_eq!(game.frame_dt(), 0.);
+        }
+
+        // A tick for every tick duration of the clock, leftovers carry over to the next frame 
+        // and a frame far behind runs MAX_TICKS_PER_FRAME of them
+        #[test]
+        fn ticks_follow_the_clock() 
+        {
+            let clock = MockClock::new();
+            let mut game = Game::new();
+            game.set_clock(Box::new(clock.clone()));
+            game.set_tick_duration(Duration::from_millis(50));
+
+            let mut frame = |by: Duration| {
+                clock.advance(by);
+                let dt = game.frame_dt();
+                game.step(KeyState::default(), dt);
+                (game.ticks_this_frame(), game.tick_count())
+            };
+
+            assert_eq!(frame(Duration::from_millis(150)), (3, 3));
+            assert_eq!(frame(Duration::from_millis(25)), (0, 3));
+            assert_eq!(frame(Duration::from_millis(25)), (1, 4));
+            assert_eq!(frame(Duration::from_millis(50) * 20), (MAX_TICKS_PER_FRAME, 4 + MAX_TICKS_PER_FRAME as u64));
+            assert_eq!(frame(Duration::from_millis(50)), (1, 5 + MAX_TICKS_PER_FRAME as u64));
+        }
+
+        // Autorepeat flickering a held key between frames is one press, letting go for 
+        // longer than the window and pressing again is the next one
+        #[test]
+        fn debounce_toggles_once_per_physical_press() 
+        {
+            let p = KeyState::default().with(Key::P);
+            let none = KeyState::default();
+            let flicker = [p, none, p, none, p, p, none, p];
+            let toggles = |window: Duration| {
+                let mut game = Game::new();
+                game.set_debounce(window);
+                let mut count = 0;
+
+                for _ in 0..2 
+                {
+                    for keys in flicker {
+                        let paused = game.paused;
+                        game.step(keys, 0.016);
+                        count += (game.paused != paused) as u32;
+                    }
+                    for _ in 0..10 {
+                        game.step(none, 0.016);
+                    }
+                }
+
+                count
+            };
+
+            assert_eq!(toggles(Duration::from_millis(100)), 2);
+            assert_eq!(toggles(Duration::ZERO), 8);
+        }
+
+        // Presses that came between two frames all count, in the order they came
+        #[test]
+        fn step_with_events_handles_every_press() 
+        {
+            let press = |key, millis| KeyEvent { key, down: true, at: Duration::from_millis(millis) };
+            let mut game = Game::new();
+
+            game.step_with_events(KeyState::default(), &[press(Key::P, 0), press(Key::P, 5)], 0.016);
+            assert!(!game.paused);
+
+            game.step_with_events(KeyState::default(), &[press(Key::P, 20), press(Key::C, 21), press(Key::P, 22)], 0.016);
+            assert!(!game.paused);
+            assert!(game.spectating);
+        }
+    }
+}
+
+// Settings read from a key = value file, anything missing keeps its default
+pub mod config 
+{
+    use crate::game_logic::{
+        Palette,
+        Action,
+        KeyBindings,
+        PLAYER_MOVEMENT_SPEED,
+        PLAYER_ROTATION_SPEED,
+        DEFAULT_FOV,
+        DEFAULT_VERTICAL_FOV,
+        MAX_VERTICAL_FOV,
+        DEFAULT_CELL_ASPECT,
+        MAX_COLUMN_STEP,
+        MAX_RAY_COUNT,
+        DEFAULT_GAMMA,
+        DEFAULT_BOUNDARY_EPSILON,
+        MAX_BOUNDARY_EPSILON,
+        MIN_GAMMA,
+        MAX_GAMMA,
+        DEFAULT_TIME_SCALE,
+        MAX_TIME_SCALE,
+        TICK_DURATION,
+        DEFAULT_VIEW_BOB_AMPLITUDE,
+        MAX_VIEW_BOB_AMPLITUDE,
+        DEFAULT_VIEW_BOB_FREQUENCY,
+        MAX_VIEW_BOB_FREQUENCY,
+        MIN_TICK_DURATION,
+        MAX_TICK_DURATION,
+        DEFAULT_VISIBLE_DISTANCE,
+        DEFAULT_COMPASS_POSITION,
+        DEFAULT_INTERACT_PROMPT,
+        DEFAULT_INTERACT_RANGE,
+        MAX_INTERACT_RANGE,
+        DEFAULT_SEED};
+    use crate::Vec2;
+    use crate::terminal::input::keys::Key;
+    use crate::terminal::input::{DEFAULT_KEY_RELEASE_DELAY, MAX_DEBOUNCE, MAX_KEY_RELEASE_DELAY};
+    use crate::terminal::output::{ColorMode, RenderPreset, CROSSHAIR_CHAR, DEFAULT_MAX_CELLS, DEFAULT_FRAME_CELL_WIDTH};
+
+    // texture_<material> = <path>, <ticks per frame>, the ticks only matter for animated art
+    pub struct TextureSetting 
+    {
+        pub material: i32,
+        pub path: String,
+        pub ticks_per_frame: u32,
+    }
+
+    pub struct Config 
+    {
+        pub movement_speed: f32,
+        pub rotation_speed: f32,
+        pub fov: f32,
+        // Degrees, 0 derives it from fov
+        pub vertical_fov: f32,
+        pub cell_aspect: f32,
+        pub column_step: u8,
+        pub ray_count: u32,
+        pub gamma: f32,
+        pub time_scale: f32,
+        // Milliseconds
+        pub tick_ms: u64,
+        // Milliseconds
+        pub debounce_ms: u64,
+        // Milliseconds
+        pub key_release_ms: u64,
+        // The look starts out as the preset's, the settings on lines after it are Some, 
+        // see Game::apply_look()
+        pub preset: RenderPreset,
+        pub side_shade: Option<f32>,
+        pub distance_fog: Option<bool>,
+        pub bold_colors: Option<bool>,
+        pub palette: Option<Palette>,
+        pub view_bob: bool,
+        pub view_bob_amplitude: f32,
+        pub view_bob_frequency: f32,
+        pub screen_shake: bool,
+        pub boundary_epsilon: f32,
+        pub max_visible_distance: i32,
+        pub seed: u64,
+        pub show_fps: bool,
+        pub show_compass: bool,
+        pub show_fov_cone: bool,
+        pub debug_hits: bool,
+        pub fog_of_war: bool,
+        pub compass_position: Vec2<i32>,
+        pub show_crosshair: bool,
+        pub crosshair_char: u8,
+        // Toggles go by the keyboard's key events instead of the keys' state every frame
+        pub key_events: bool,
+        // Empty hides it, the range is in cells
+        pub interact_prompt: String,
+        pub interact_range: f32,
+        pub max_cells: usize,
+        pub bindings: KeyBindings,
+        // None keeps the terminal's default
+        pub color_mode: Option<ColorMode>,
+        // Path of a map to start in instead of the built in one
+        pub map: Option<String>,
+        // Paths of the maps that follow the first one, in order
+        pub levels: Vec<String>,
+        // Overrides the first map's spawn, world units and degrees clockwise from north
+        pub spawn: Option<Vec2<f32>>,
+        pub spawn_yaw: Option<f32>,
+        pub show_weapon: bool,
+        // Path of the weapon's character art instead of the built in one
+        pub weapon: Option<String>,
+        // Character art drawn on the walls of a material, in the order they were set
+        pub textures: Vec<TextureSetting>,
+        // Waypoints in world units, the player walks through them by themselves when there are any
+        pub autopilot: Vec<Vec2<f32>>,
+        // Directory every frame is saved to as an image, up to record_frames_limit of them
+        pub record_frames: Option<String>,
+        pub record_frames_limit: u32,
+        pub record_cell_width: usize,
+        pub record_color: bool,
+    }
+
+    impl Default for Config 
+    {
+        fn default() -> Config 
+        {
+            Config {
+                movement_speed: PLAYER_MOVEMENT_SPEED,
+                rotation_speed: PLAYER_ROTATION_SPEED,
+                fov: DEFAULT_FOV,
+                vertical_fov: DEFAULT_VERTICAL_FOV,
+                cell_aspect: DEFAULT_CELL_ASPECT,
+                column_step: 1,
+                ray_count: 0,
+                gamma: DEFAULT_GAMMA,
+                time_scale: DEFAULT_TIME_SCALE,
+                tick_ms: TICK_DURATION.as_millis() as u64,
+                debounce_ms: 0,
+                key_release_ms: DEFAULT_KEY_RELEASE_DELAY.as_millis() as u64,
+                preset: RenderPreset::Default,
+                side_shade: None,
+                distance_fog: None,
+                bold_colors: None,
+                palette: None,
+                view_bob: false,
+                view_bob_amplitude: DEFAULT_VIEW_BOB_AMPLITUDE,
+                view_bob_frequency: DEFAULT_VIEW_BOB_FREQUENCY,
+                screen_shake: true,
+                boundary_epsilon: DEFAULT_BOUNDARY_EPSILON,
+                max_visible_distance: DEFAULT_VISIBLE_DISTANCE,
+                seed: DEFAULT_SEED,
+                show_fps: false,
+                show_compass: false,
+                show_fov_cone: false,
+                debug_hits: false,
+                fog_of_war: false,
+                compass_position: DEFAULT_COMPASS_POSITION,
+                show_crosshair: true,
+                crosshair_char: CROSSHAIR_CHAR,
+                key_events: false,
+                interact_prompt: DEFAULT_INTERACT_PROMPT.to_string(),
+                interact_range: DEFAULT_INTERACT_RANGE,
+                max_cells: DEFAULT_MAX_CELLS,
+                bindings: KeyBindings::default(),
+                color_mode: None,
+                map: None,
+                levels: Vec::new(),
+                spawn: None,
+                spawn_yaw: None,
+                show_weapon: true,
+                weapon: None,
+                textures: Vec::new(),
+                autopilot: Vec::new(),
+                record_frames: None,
+                record_frames_limit: 300,
+                record_cell_width: DEFAULT_FRAME_CELL_WIDTH,
+                record_color: true,
+            }
+        }
+    }
+
+    impl Config 
+    {
+        // Defaults when there's no file at path, errors only for a file that's there and wrong
+        pub fn load_or_default(path: &str) -> Result<Config, String> 
+        {
+            if !std::path::Path::new(path).exists() {
+                return Ok(Config::default());
+            }
+
+            Config::from_file(path)
+        }
+
+        pub fn from_file(path: &str) -> Result<Config, String> 
+        {
+            let text = std::fs::read_to_string(path)
+                .map_err(|err| format!("Cannot read config {}: {}", path, err))?;
+
+            Config::parse(&text).map_err(|err| format!("Config {}: {}", path, err))
+        }
+
+        pub fn parse(text: &str) -> Result<Config, String> 
+        {
+            let mut config = Config::default();
+
+            for (i, line) in text.lines().enumerate() 
+            {
+                let line = line.trim();
+
+                // Whole line comments only, '#' is a fine wall character
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                let (key, value) = match line.split_once('=') {
+                    Some((key, value)) => (key.trim(), value.trim().trim_matches('"')),
+                    None => return Err(format!("line {}: expected key = value", i + 1)),
+                };
+
+                config.set(key, value).map_err(|err| format!("line {}: {}", i + 1, err))?;
+            }
+
+            Ok(config)
+        }
+
+        fn set(&mut self,
+               key: &str,
+               value: &str) -> Result<(), String> 
+        {
+            match key 
+            {
+                "movement_speed" => self.movement_speed = parse_in_range(key, value, 0.01, 100.)?,
+                "rotation_speed" => self.rotation_speed = parse_in_range(key, value, 0.01, 20.)?,
+                "fov" => self.fov = parse_in_range(key, value, 1., 360.)?,
+                "vertical_fov" => self.vertical_fov = parse_in_range(key, value, 0., MAX_VERTICAL_FOV)?,
+                "cell_aspect" => self.cell_aspect = parse_in_range(key, value, 0.25, 8.)?,
+                "column_step" => self.column_step = parse_in_range(key, value, 1, MAX_COLUMN_STEP)?,
+                "ray_count" => self.ray_count = parse_in_range(key, value, 0, MAX_RAY_COUNT)?,
+                "gamma" => self.gamma = parse_in_range(key, value, MIN_GAMMA, MAX_GAMMA)?,
+                "time_scale" => self.time_scale = parse_in_range(key, value, 0., MAX_TIME_SCALE)?,
+                "tick_ms" => self.tick_ms = parse_in_range(key, value, 
+                                                           MIN_TICK_DURATION.as_millis() as u64, 
+                                                           MAX_TICK_DURATION.as_millis() as u64)?,
+                "key_events" => self.key_events = parse_in_range(key, value, false, true)?,
+                "debounce_ms" => self.debounce_ms = parse_in_range(key, value, 0, MAX_DEBOUNCE.as_millis() as u64)?,
+                "key_release_ms" => self.key_release_ms = parse_in_range(key, value, 0, MAX_KEY_RELEASE_DELAY.as_millis() as u64)?,
+                "side_shade" => self.side_shade = Some(parse_in_range(key, value, 0., 1.)?),
+                "distance_fog" => self.distance_fog = Some(parse_in_range(key, value, false, true)?),
+                "bold_colors" => self.bold_colors = Some(parse_in_range(key, value, false, true)?),
+                "view_bob" => self.view_bob = parse_in_range(key, value, false, true)?,
+                "view_bob_amplitude" => self.view_bob_amplitude = parse_in_range(key, value, 0., MAX_VIEW_BOB_AMPLITUDE)?,
+                "view_bob_frequency" => self.view_bob_frequency = parse_in_range(key, value, 0., MAX_VIEW_BOB_FREQUENCY)?,
+                "screen_shake" => self.screen_shake = parse_in_range(key, value, false, true)?,
+                // Sets every setting the preset bundles, the lines after it can still change them
+                "preset" => {
+                    self.preset = RenderPreset::from_name(value)
+                        .ok_or_else(|| format!("preset is one of default, high_contrast, got \"{}\"", value))?;
+                    self.side_shade = None;
+                    self.distance_fog = None;
+                    self.bold_colors = None;
+                    self.palette = None;
+                }
+                "boundary_epsilon" => self.boundary_epsilon = parse_in_range(key, value, 0., MAX_BOUNDARY_EPSILON)?,
+                "max_visible_distance" => self.max_visible_distance = parse_in_range(key, value, 1, 1000)?,
+                "seed" => self.seed = parse_in_range(key, value, 1, u64::MAX)?,
+                "show_fps" => self.show_fps = parse_in_range(key, value, false, true)?,
+                "show_compass" => self.show_compass = parse_in_range(key, value, false, true)?,
+                "show_fov_cone" => self.show_fov_cone = parse_in_range(key, value, false, true)?,
+                "debug_hits" => self.debug_hits = parse_in_range(key, value, false, true)?,
+                "fog_of_war" => self.fog_of_war = parse_in_range(key, value, false, true)?,
+                "compass_position" => {
+                    match value.split_once(',') {
+                        Some((x, y)) => {
+                            self.compass_position = Vec2 { 
+                                x: parse_in_range(key, x.trim(), 0, i16::MAX as i32)?,
+                                y: parse_in_range(key, y.trim(), 0, i16::MAX as i32)?,
+                            };
+                        }
+                        None => return Err(format!("compass_position needs a column and a row, got \"{}\"", value)),
+                    }
+                }
+                "show_crosshair" => self.show_crosshair = parse_in_range(key, value, false, true)?,
+                "crosshair_char" => {
+                    match value.as_bytes() {
+                        [ch] if ch.is_ascii_graphic() => self.crosshair_char = *ch,
+                        _ => return Err(format!("crosshair_char needs one visible character, got \"{}\"", value)),
+                    }
+                }
+                "interact_prompt" => self.interact_prompt = value.to_string(),
+                "interact_range" => self.interact_range = parse_in_range(key, value, 0., MAX_INTERACT_RANGE)?,
+                "wall_chars" => {
+                    match value.as_bytes() {
+                        [x, y] if x.is_ascii_graphic() && y.is_ascii_graphic() => {
+                            self.palette = Some(Palette { wall_x: *x, wall_y: *y });
+                        }
+                        _ => return Err(format!("wall_chars needs two visible characters, got \"{}\"", value)),
+                    }
+                }
+                "color_mode" => {
+                    self.color_mode = Some(match value {
+                        "monochrome" => ColorMode::Monochrome,
+                        "ansi256" => ColorMode::Ansi256,
+                        "truecolor" => ColorMode::TrueColor,
+                        _ => return Err(format!("color_mode is one of monochrome, ansi256, truecolor, got \"{}\"", value)),
+                    });
+                }
+                "max_cells" => self.max_cells = parse_in_range(key, value, 1, 10_000_000)?,
+                // Left over from before every action could be bound
+                "quit_key" => self.bindings.bind(Action::Quit, &[parse_key(key, value)?]),
+                "map" => self.map = Some(value.to_string()),
+                "show_weapon" => self.show_weapon = parse_in_range(key, value, false, true)?,
+                "weapon" => self.weapon = Some(value.to_string()),
+                "record_frames" => self.record_frames = Some(value.to_string()),
+                "record_frames_limit" => self.record_frames_limit = parse_in_range(key, value, 1, 100_000)?,
+                "record_cell_width" => self.record_cell_width = parse_in_range(key, value, 1, 32)?,
+                "record_color" => self.record_color = parse_in_range(key, value, false, true)?,
+                "spawn" => self.spawn = Some(parse_position(key, value)?),
+                "spawn_yaw" => self.spawn_yaw = Some(parse_in_range(key, value, 0., 360.)?),
+                "autopilot" => {
+                    self.autopilot.clear();
+
+                    for point in value.split(';').map(str::trim).filter(|point| !point.is_empty()) 
+                    {
+                        self.autopilot.push(parse_position(key, point)?);
+                    }
+                }
+                "levels" => {
+                    self.levels = value.split(',')
+                        .map(str::trim)
+                        .filter(|path| !path.is_empty())
+                        .map(String::from)
+                        .collect();
+                }
+                _ if key.starts_with("bind_") => self.set_binding(key, value)?,
+                _ if key.starts_with("texture_") => self.set_texture(key, value)?,
+                _ => return Err(format!("unknown setting \"{}\"", key)),
+            }
+
+            Ok(())
+        }
+
+        // Setting the same material again replaces its texture
+        fn set_texture(&mut self,
+                       key: &str,
+                       value: &str) -> Result<(), String> 
+        {
+            let material = parse_in_range(key, &key["texture_".len()..], 1, 8)?;
+            let (path, ticks_per_frame) = match value.rsplit_once(',') {
+                Some((path, ticks)) => (path.trim(), parse_in_range(key, ticks.trim(), 1, 1000)?),
+                None => (value, 1),
+            };
+
+            self.textures.retain(|setting| setting.material != material);
+            self.textures.push(TextureSetting { material, path: path.trim_matches('"').to_string(), ticks_per_frame });
+            Ok(())
+        }
+
+        // bind_<action> = <key>, <key>, an empty list leaves the action unbound
+        fn set_binding(&mut self,
+                       key: &str,
+                       value: &str) -> Result<(), String> 
+        {
+            let action = Action::from_name(&key["bind_".len()..])
+                .ok_or_else(|| format!("unknown setting \"{}\"", key))?;
+            let keys = value.split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(|name| parse_key(key, name))
+                .collect::<Result<Vec<Key>, String>>()?;
+
+            self.bindings.bind(action, &keys);
+            Ok(())
+        }
+    }
+
+    fn parse_key(key: &str,
+                 value: &str) -> Result<Key, String> 
+    {
+        Key::from_name(value).ok_or_else(|| format!("{} has an unknown key \"{}\"", key, value))
+    }
+
+    // A point on the map in world units, "x, y" or "[x, y]", neither can be negative
+    fn parse_position(key: &str,
+                      value: &str) -> Result<Vec2<f32>, String> 
+    {
+        let pos = value.parse::<Vec2<f32>>()
+            .map_err(|err| format!("{} {}, got \"{}\"", key, err, value))?;
+
+        if !(0. ..=f32::MAX).contains(&pos.x) || !(0. ..=f32::MAX).contains(&pos.y) {
+            return Err(format!("{} has to be between 0 and {}, got \"{}\"", key, f32::MAX, value));
+        }
+
+        Ok(pos)
+    }
+
+    fn parse_in_range<T>(key: &str,
+                         value: &str,
+                         min: T,
+                         max: T) -> Result<T, String> 
+        where T: std::str::FromStr + PartialOrd + std::fmt::Display
+    {
+        match value.parse::<T>() {
+            Ok(parsed) if parsed >= min && parsed <= max => Ok(parsed),
+            Ok(_) => Err(format!("{} has to be between {} and {}, got {}", key, min, max, value)),
+            Err(_) => Err(format!("{} has a bad value \"{}\"", key, value)),
+        }
+    }
+}
+
+// Per frame inputs saved to a text file, one "<held keys bits> <dt>" line per frame. 
+// Played back into a game with the same seed they reproduce the session exactly
+pub mod replay 
+{
+    use std::fs::File;
+    use std::io::{BufRead, BufReader, BufWriter, Error, ErrorKind, Write};
+    use crate::terminal::input::keys::KeyState;
+
+    pub struct InputRecorder 
+    {
+        file: BufWriter<File>,
+    }
+
+    impl InputRecorder 
+    {
+        pub fn create(path: &str) -> std::io::Result<InputRecorder> 
+        {
+            Ok(InputRecorder { file: BufWriter::new(File::create(path)?) })
+        }
+
+        pub fn record(&mut self,
+                      keys: KeyState,
+                      dt: f32) -> std::io::Result<()> 
+        {
+            writeln!(self.file, "{} {}", keys.bits(), dt)
+        }
+    }
+
+    pub struct InputPlayer 
+    {
+        frames: Vec<(KeyState, f32)>,
+        next: usize,
+    }
+
+    impl InputPlayer 
+    {
+        pub fn open(path: &str) -> std::io::Result<InputPlayer> 
+        {
+            let mut frames = Vec::new();
+
+            for line in BufReader::new(File::open(path)?).lines() 
+            {
+                let line = line?;
+                let mut fields = line.split_whitespace();
+
+                let frame = match (fields.next(), fields.next()) {
+                    (Some(key), Some(dt)) => key.parse::<u32>().ok().zip(dt.parse::<f32>().ok()),
+                    _ => None,
+                };
+
+                match frame {
+                    Some((keys, dt)) => frames.push((KeyState::from_bits(keys), dt)),
+                    None => return Err(Error::new(ErrorKind::InvalidData, format!("Bad replay frame \"{}\"", line))),
+                }
+            }
+
+            Ok(InputPlayer { frames, next: 0 })
+        }
+
+        // None once every recorded frame was played
+        pub fn next_frame(&mut self) -> Option<(KeyState, f32)> 
+        {
+            let frame = self.frames.get(self.next).copied();
+            self.next += 1;
+
+            frame
+        }
+    }
+
+    #[cfg(test)]
+    mod tests 
+    {
+        use super::*;
+        use crate::game_logic::Game;
+        use crate::terminal::input::keys::Key;
+
+        fn temp_path(name: &str) -> String 
+        {
+            std::env::temp_dir()
+                .join(format!("wolfenstein_{}_{}", std::process::id(), name))
+                .to_string_lossy()
+                .into_owned()
+        }
+
+        // A recorded session played back into a game with the same seed ends up in the same place
+        #[test]
+        fn replay_reproduces_the_session() 
+        {
+            let path = temp_path("session.replay");
+            let walk = KeyState::default().with(Key::W);
+            let script = [(walk, 0.05), (walk.with(Key::E), 0.033), (KeyState::default().with(Key::Space), 0.016), 
+                          (walk.with(Key::Q), 0.041), (walk.with(Key::D), 0.05)];
+
+            let mut recorded = Game::with_seed(7);
+            let mut recorder = InputRecorder::create(&path).unwrap();
+            for (keys, dt) in script.iter().cycle().take(60) 
+            {
+                recorder.record(*keys, *dt).unwrap();
+                recorded.step(*keys, *dt);
+            }
+            drop(recorder);
+
+            let mut replayed = Game::with_seed(7);
+            let mut player = InputPlayer::open(&path).unwrap();
+            while let Some((keys, dt)) = player.next_frame() {
+                replayed.step(keys, dt);
+            }
+            std::fs::remove_file(&path).unwrap();
+
+            let (a, b) = (recorded.player_position(), replayed.player_position());
+            let spawn = Game::with_seed(7).player_position();
+            assert!(a.x != spawn.x || a.y != spawn.y);
+            assert_eq!((a.x, a.y), (b.x, b.y));
+            assert_eq!(recorded.player_yaw(), replayed.player_yaw());
+        }
+
+        #[test]
+        fn malformed_frames_are_invalid_data() 
+        {
+            let path = temp_path("malformed.replay");
+            std::fs::write(&path, "1 0.05\nforward 0.05\n").unwrap();
+
+            let err = InputPlayer::open(&path).err().unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+}
+
+// Plays the game for one remote client. Frames go out as ANSI over the socket and the 
+// client's keystrokes come back the way a raw mode terminal sends them, so telnet, or 
+// netcat in a raw terminal, is all the client needs
+pub mod net 
+{
+    use std::io::{self, ErrorKind, Read, Write};
+    use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use crate::config::Config;
+    use crate::game_logic::{Action, Game, ViewMode};
+    use crate::terminal::input::keys::{Key, KeyState};
+    use crate::terminal::input::{Debounce, InputSource, KeyTracker};
+    use crate::terminal::output::Renderer;
+
+    // Clients don't tell their size, 80x24 cells fits about every one of them. 
+    // Screen units, two per row
+    const CLIENT_SCREEN: (i16, i16) = (80, 48);
+
+    const FRAME_MS: u64 = 50;
+
+    // Telnet starts in line mode with local echo, IAC WILL ECHO and IAC WILL SUPPRESS-GO-AHEAD 
+    // make it send every key as it's typed. Other clients show two odd characters at most
+    const TELNET_CHARACTER_MODE: &[u8] = &[255, 251, 1, 255, 251, 3];
+
+    pub struct NetBackend 
+    {
+        stream: TcpStream,
+        closed: Arc<AtomicBool>,
+    }
+
+    // The renderer expects its writer to never fail, so a dropped connection 
+    // is only remembered here and the frame loop ends on it
+    struct ClientWriter 
+    {
+        stream: TcpStream,
+        closed: Arc<AtomicBool>,
+    }
+
+    impl Write for ClientWriter 
+    {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> 
+        {
+            if self.stream.write_all(buf).is_err() {
+                self.closed.store(true, Ordering::Relaxed);
+            }
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> 
+        {
+            if self.stream.flush().is_err() {
+                self.closed.store(true, Ordering::Relaxed);
+            }
+
+            Ok(())
+        }
+    }
+
+    // Keys typed on the client, read without waiting for them
+    struct NetInput 
+    {
+        stream: TcpStream,
+        tracker: KeyTracker,
+        hung_up: bool,
+    }
+
+    impl NetInput 
+    {
+        fn new(stream: TcpStream, 
+               release_delay: Duration) -> NetInput 
+        {
+            NetInput { stream, tracker: KeyTracker::with_release_delay(release_delay), hung_up: false }
+        }
+
+        // The client disconnected, or the connection broke
+        fn hung_up(&self) -> bool 
+        {
+            self.hung_up
+        }
+    }
+
+    impl InputSource for NetInput 
+    {
+        fn poll(&mut self) -> KeyState 
+        {
+            let mut buf = [0_u8; 64];
+
+            match self.stream.read(&mut buf) 
+            {
+                Ok(0) => self.hung_up = true,
+                Ok(n) => self.tracker.press_bytes(&buf[..n]),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(_) => self.hung_up = true,
+            }
+
+            self.tracker.poll().1
+        }
+    }
+
+    impl NetBackend 
+    {
+        // Waits for one client on addr and plays until it quits or disconnects
+        pub fn serve<A: ToSocketAddrs>(addr: A,
+                                       config: &Config,
+                                       game: Game) -> io::Result<()> 
+        {
+            let listener = TcpListener::bind(addr)?;
+            let (stream, _) = listener.accept()?;
+
+            NetBackend::new(stream)?.run(config, game)
+        }
+
+        fn new(stream: TcpStream) -> io::Result<NetBackend> 
+        {
+            // Reads only wait a moment, so a quiet client doesn't stall the frames
+            stream.set_read_timeout(Some(Duration::from_millis(1)))?;
+            stream.set_nodelay(true)?;
+
+            Ok(NetBackend {
+                stream,
+                closed: Arc::new(AtomicBool::new(false)),
+            })
+        }
+
+        fn run(&mut self,
+               config: &Config,
+               mut game: Game) -> io::Result<()> 
+        {
+            let writer = ClientWriter {
+                stream: self.stream.try_clone()?,
+                closed: self.closed.clone(),
+            };
+            let mut render = Renderer::with_writer(Box::new(writer));
+
+            render.set_dimensions(CLIENT_SCREEN.0, CLIENT_SCREEN.1);
+            render.set_max_cells(config.max_cells);
+
+            if let Some(mode) = config.color_mode {
+                render.set_color_mode(mode);
+            }
+            game.apply_look(&mut render, config);
+
+            self.stream.write_all(TELNET_CHARACTER_MODE)?;
+
+            let mut input = NetInput::new(self.stream.try_clone()?, Duration::from_millis(config.key_release_ms));
+            let mut mode = ViewMode::Mode3d;
+            let mut debounce = Debounce::new(Duration::from_millis(config.debounce_ms));
+            let mut last_held = KeyState::default();
+
+            while !self.closed.load(Ordering::Relaxed) 
+            {
+                sleep(Duration::from_millis(FRAME_MS));
+
+                let held = input.poll();
+                if input.hung_up() {
+                    break;
+                }
+
+                let dt = game.frame_dt();
+                let debounced = debounce.filter(held, dt);
+                if config.bindings.is_down(Action::ToggleView, debounced) && !config.bindings.is_down(Action::ToggleView, last_held) {
+                    mode = mode.next();
+                }
+                last_held = debounced;
+
+                render.update();
+                game.update_with_dt(&mut render, held, mode, dt);
+                render.render();
+
+                if config.bindings.is_down(Action::Quit, held) || held.is_down(Key::Interrupt) {
+                    break;
+                }
+            }
+
+            render.shutdown();
+            Ok(())
+        }
+    }
+}
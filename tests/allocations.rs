@@ -0,0 +1,96 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use wolfenstein::game_logic::{Game, ViewMode};
+use wolfenstein::terminal::input::keys::KeyState;
+use wolfenstein::terminal::output::Renderer;
+
+// Counts the allocations of the thread that turned counting on, the test harness's other threads 
+// allocate whenever they like
+struct CountingAllocator;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator 
+{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 
+    {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) 
+    {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 
+    {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count() 
+{
+    // try_with, a thread that's going away has no locals anymore
+    let _ = COUNTING.try_with(|counting| {
+        if counting.get() {
+            ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        }
+    });
+}
+
+// Allocations made while frame runs
+fn allocations_in(frame: impl FnOnce()) -> usize 
+{
+    ALLOCATIONS.with(|allocations| allocations.set(0));
+    COUNTING.with(|counting| counting.set(true));
+    frame();
+    COUNTING.with(|counting| counting.set(false));
+
+    ALLOCATIONS.with(|allocations| allocations.get())
+}
+
+fn frame(game: &mut Game, 
+         output: &mut Renderer, 
+         mode: ViewMode) 
+{
+    output.update();
+    game.step(KeyState::default(), 0.05);
+    game.render(output, mode);
+    output.render();
+}
+
+// Every buffer a frame needs is there after the first few, the rest reuse them
+#[test]
+fn render_loop_allocates_nothing_once_warm() 
+{
+    assert_eq!(allocations_in(|| drop(std::hint::black_box(Vec::<u8>::with_capacity(16)))), 1);
+
+    for mode in [ViewMode::Mode3d, ViewMode::Mode2d, ViewMode::Mode2dAnd3d, ViewMode::Wireframe] 
+    {
+        let mut output = Renderer::with_writer(Box::new(std::io::sink()));
+        let mut game = Game::new();
+        output.set_dimensions(120, 80);
+        game.set_show_fps(true);
+
+        for _ in 0..10 {
+            frame(&mut game, &mut output, mode);
+        }
+
+        let allocations = allocations_in(|| {
+            for _ in 0..100 {
+                frame(&mut game, &mut output, mode);
+            }
+        });
+
+        assert_eq!(allocations, 0, "{:?}", mode);
+    }
+}